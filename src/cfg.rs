@@ -1,21 +1,223 @@
 use crate::cfg::TransactionType::*;
+use crate::profile::TimeScale;
+use crate::tpcc::{district_of_terminal, NuRandConstants};
+use crate::util::Error;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 use std::slice::Iter;
+use structopt::StructOpt;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Schema version of the configs written by this version of the library
+pub const CFG_SCHEMA_VERSION: u32 = 5;
+
+/// Schema version of the configs written before the version was recorded in the header
+pub const LEGACY_CFG_SCHEMA_VERSION: u32 = 1;
+
+/// Terminal config. The fields added after the schema version 1 are optional, so legacy configs can be read and migrated
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct TermControlCfg {
+    #[serde(default)]
+    pub header: CfgHeader,
     pub home_warehouse_id: u32,
     pub this_terminal_id: u32,
+    /// District of the home warehouse the terminal is bound to, see TPC-C Clause 5.5.1.1.
+    /// The StockLevel transactions of the terminal run against it, see TPC-C Clause 2.8.1.1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub district_id: Option<u32>,
+    /// Time the terminal waits before its first transaction, staggers the ramp-up
+    #[serde(default)]
+    pub start_delay_ms: u64,
+    /// Global seed of the run, the terminal's own seed is derived from it and the terminal's ids.
+    /// Unknown for legacy configs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub think_time_distribution: Vec<ThinkTimeDistribution>,
     pub transactions_to_run: Vec<TransactionParams>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl TermControlCfg {
+    /// Upgrade the config to the current schema version. Returns false if it is up to date already
+    pub fn migrate(&mut self) -> bool {
+        if self.header.schema_version >= CFG_SCHEMA_VERSION {
+            return false;
+        }
+        // Version 1 lacks fields only, their defaults are filled in on reading.
+        // Version 2 lacks the district, it is derived from the terminal id the way `generate` assigns it.
+        // Version 3 lacks the time scale and the non-compliance reasons, the legacy runs are taken as unscaled.
+        // Version 4 lacks the arrival schedule, the legacy runs are closed-loop
+        if self.district_id.is_none() {
+            self.district_id = Some(district_of_terminal(self.this_terminal_id));
+        }
+        self.header.schema_version = CFG_SCHEMA_VERSION;
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct TransactionParams {
-    #[serde(rename(serialize = "type"))]
+    #[serde(rename = "type")]
     pub typ: TransactionType,
     pub keying_time_ms: u32,
     pub think_time_ms: u32,
     pub is_rbk: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<TransactionInput>,
+    /// Intended start of the transaction from the end of the terminal's start delay.
+    /// Set by the open-loop schedules only, the delay against it is the emulator's to measure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_offset_ms: Option<u64>,
+}
+
+/// Terminal input data of a transaction, see TPC-C Clauses 2.4 - 2.8
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum TransactionInput {
+    NewOrder(NewOrderInput),
+    Payment(PaymentInput),
+    OrderStatus(OrderStatusInput),
+    Delivery(DeliveryInput),
+    StockLevel(StockLevelInput),
+}
+
+/// NewOrder input data, see TPC-C Clause 2.4.1
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct NewOrderInput {
+    pub d_id: u32,
+    pub c_id: u32,
+    pub order_lines: Vec<OrderLineInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OrderLineInput {
+    pub ol_i_id: u32,
+    pub ol_supply_w_id: u32,
+    pub ol_quantity: u32,
+}
+
+/// Payment input data, see TPC-C Clause 2.5.1
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PaymentInput {
+    pub d_id: u32,
+    pub c_w_id: u32,
+    pub c_d_id: u32,
+    pub customer: CustomerSelector,
+    pub h_amount: f64,
+}
+
+/// OrderStatus input data, see TPC-C Clause 2.6.1
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OrderStatusInput {
+    pub d_id: u32,
+    pub customer: CustomerSelector,
+}
+
+/// Delivery input data, see TPC-C Clause 2.7.1
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeliveryInput {
+    pub o_carrier_id: u32,
+}
+
+/// StockLevel input data, see TPC-C Clause 2.8.1
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct StockLevelInput {
+    pub threshold: u32,
+}
+
+/// Customer selection either by the last name or by the customer id
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum CustomerSelector {
+    ById(u32),
+    ByLastName(String),
+}
+
+/// Run-wide parameters shared by all the terminals, kept for the audit and for the repeatability
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CfgHeader {
+    /// Schema version the config is written in, the configs newer than `CFG_SCHEMA_VERSION` are rejected
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    /// NURand C constants of the input data. Unknown for legacy configs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nurand: Option<NuRandConstants>,
+    /// Scale factors the keying and think times were generated with, absent for unscaled runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_scale: Option<TimeScale>,
+    /// TPC-C rules the run breaks, generated with `--non-compliant`. Empty for compliant runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub non_compliance: Vec<String>,
+    /// Open-loop arrival schedule the start offsets of the transactions are drawn from, absent for closed-loop runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<ArrivalSchedule>,
+}
+
+impl Default for CfgHeader {
+    fn default() -> Self {
+        CfgHeader {
+            schema_version: LEGACY_CFG_SCHEMA_VERSION,
+            nurand: None,
+            time_scale: None,
+            non_compliance: Vec::new(),
+            arrival: None,
+        }
+    }
+}
+
+fn legacy_schema_version() -> u32 {
+    LEGACY_CFG_SCHEMA_VERSION
+}
+
+/// Just the schema version of a config, read before the config itself,
+/// so a config newer than the library is reported as such rather than as a format error
+#[derive(Deserialize)]
+struct SchemaVersionProbe {
+    #[serde(default)]
+    header: CfgHeader,
+}
+
+arg_enum! {
+    /// Arrival process of an open-loop schedule.
+    /// 'Poisson' draws exponential inter-arrival times, 'Fixed' keeps them constant
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum ArrivalProcess {
+        Poisson,
+        Fixed
+    }
+}
+
+/// Open-loop schedule: every transaction starts at its offset drawn from the arrival process
+/// instead of after the keying and think times of the previous one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ArrivalSchedule {
+    pub process: ArrivalProcess,
+    /// Target arrival rate of a terminal in transactions per minute
+    pub rate_tpm: f64,
+}
+
+impl ArrivalSchedule {
+    pub fn check(&self) -> Result<(), Error> {
+        if !self.rate_tpm.is_finite() || self.rate_tpm <= 0. {
+            return Err(Error {
+                err_msg: format!("Arrival rate must be more than 0, got {}", self.rate_tpm),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn mean_interval_ms(&self) -> f64 {
+        60_000. / self.rate_tpm
+    }
+}
+
+/// Parameters of the negative exponential distribution the think times are drawn from
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ThinkTimeDistribution {
+    #[serde(rename = "type")]
+    pub typ: TransactionType,
+    pub mean_ms: u32,
+    pub max_ms: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
@@ -34,3 +236,331 @@ impl TransactionType {
         TRANSACTION_TYPES.iter()
     }
 }
+
+arg_enum! {
+    /// Serialization format of the terminal configs.
+    /// 'Json' writes compact single-line documents, 'MessagePack' maps keyed by the field names
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum CfgFormat {
+        Yaml,
+        Json,
+        Toml,
+        MessagePack
+    }
+}
+
+impl CfgFormat {
+    /// YAML configs keep the original `.cfg` extension
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            CfgFormat::Yaml => "cfg",
+            CfgFormat::Json => "json",
+            CfgFormat::Toml => "toml",
+            CfgFormat::MessagePack => "msgpack",
+        }
+    }
+
+    pub fn from_file_extension(ext: &str) -> Option<CfgFormat> {
+        match ext {
+            "cfg" | "yaml" | "yml" => Some(CfgFormat::Yaml),
+            "json" => Some(CfgFormat::Json),
+            "toml" => Some(CfgFormat::Toml),
+            "msgpack" => Some(CfgFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    pub fn serialize_cfg(&self, cfg: &TermControlCfg) -> Result<Vec<u8>, String> {
+        match self {
+            CfgFormat::Yaml => serde_yaml::to_vec(cfg).map_err(|e| e.to_string()),
+            CfgFormat::Json => serde_json::to_vec(cfg).map_err(|e| e.to_string()),
+            CfgFormat::Toml => toml::to_string(cfg)
+                .map(String::into_bytes)
+                .map_err(|e| e.to_string()),
+            CfgFormat::MessagePack => rmp_serde::to_vec_named(cfg).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Read a config of the schema version up to `CFG_SCHEMA_VERSION`
+    pub fn deserialize_cfg(&self, doc: &[u8]) -> Result<TermControlCfg, String> {
+        let probe: SchemaVersionProbe = self.deserialize(doc)?;
+        if probe.header.schema_version > CFG_SCHEMA_VERSION {
+            return Err(format!(
+                "schema version {} is newer than the supported version {}, upgrade the tool",
+                probe.header.schema_version, CFG_SCHEMA_VERSION
+            ));
+        }
+        self.deserialize(doc)
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, doc: &[u8]) -> Result<T, String> {
+        match self {
+            CfgFormat::Yaml => serde_yaml::from_slice(doc).map_err(|e| e.to_string()),
+            CfgFormat::Json => serde_json::from_slice(doc).map_err(|e| e.to_string()),
+            CfgFormat::Toml => std::str::from_utf8(doc)
+                .map_err(|e| e.to_string())
+                .and_then(|doc| toml::from_str(doc).map_err(|e| e.to_string())),
+            CfgFormat::MessagePack => rmp_serde::from_slice(doc).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Read a single terminal config file, the format is chosen by the file extension
+pub fn read_cfg(path: &Path) -> Result<TermControlCfg, Error> {
+    let format = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(CfgFormat::from_file_extension)
+        .ok_or_else(|| Error {
+            err_msg: format!("Unknown terminal config format {:?}", path),
+        })?;
+    let doc = fs::read(path).map_err(|e| Error {
+        err_msg: format!("Error reading terminal config {:?}: {}", path, e),
+    })?;
+    format.deserialize_cfg(&doc).map_err(|e| Error {
+        err_msg: format!("Terminal config {:?} has an incorrect format: {}", path, e),
+    })
+}
+
+arg_enum! {
+    /// Layout of a config bundle holding every terminal config in a single file.
+    /// 'Yaml' writes a multi-document YAML file, 'JsonLines' one JSON document per line
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum BundleFormat {
+        Yaml,
+        JsonLines
+    }
+}
+
+impl BundleFormat {
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            BundleFormat::Yaml => "yaml",
+            BundleFormat::JsonLines => "jsonl",
+        }
+    }
+
+    /// Format of the single documents the bundle is made of
+    pub fn cfg_format(&self) -> CfgFormat {
+        match self {
+            BundleFormat::Yaml => CfgFormat::Yaml,
+            BundleFormat::JsonLines => CfgFormat::Json,
+        }
+    }
+
+    /// Single bundle document, terminated so the documents can be concatenated as they are
+    pub fn serialize_cfg(&self, cfg: &TermControlCfg) -> Result<Vec<u8>, String> {
+        let mut doc = self.cfg_format().serialize_cfg(cfg)?;
+        if doc.last() != Some(&b'\n') {
+            doc.push(b'\n');
+        }
+        Ok(doc)
+    }
+
+    pub fn deserialize_cfg(&self, doc: &[u8]) -> Result<TermControlCfg, String> {
+        self.cfg_format().deserialize_cfg(doc)
+    }
+}
+
+/// Location of a single terminal config within a bundle
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleEntry {
+    pub home_warehouse_id: u32,
+    pub this_terminal_id: u32,
+    /// Byte offset of the document from the beginning of the bundle
+    pub offset: u64,
+    /// Byte length of the document
+    pub length: u64,
+}
+
+/// Index written next to a bundle, so a single terminal config is read without parsing the whole bundle
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleIndex {
+    pub format: BundleFormat,
+    /// Bundle file name relative to the index location
+    pub bundle_file_name: String,
+    pub terminals: Vec<BundleEntry>,
+}
+
+impl BundleIndex {
+    pub fn from_file(path: &Path) -> Result<BundleIndex, Error> {
+        let content = fs::read_to_string(path).map_err(|e| Error {
+            err_msg: format!("Error reading bundle index {:?}: {}", path, e),
+        })?;
+        serde_yaml::from_str(&content).map_err(|e| Error {
+            err_msg: format!("Bundle index {:?} has an incorrect format: {}", path, e),
+        })
+    }
+
+    pub fn write(&self, path: &Path) {
+        let str = serde_yaml::to_string(self).expect("Unsupported bundle index format");
+        fs::write(path, &str)
+            .unwrap_or_else(|e| panic!("Error writing bundle index {:?}: {}", path, e));
+    }
+
+    /// Read the config of a single terminal from the bundle located in `bundle_dir`
+    pub fn read_cfg(
+        &self,
+        bundle_dir: &Path,
+        home_warehouse_id: u32,
+        this_terminal_id: u32,
+    ) -> Result<TermControlCfg, Error> {
+        let entry = self
+            .terminals
+            .iter()
+            .find(|e| {
+                e.home_warehouse_id == home_warehouse_id && e.this_terminal_id == this_terminal_id
+            })
+            .ok_or_else(|| Error {
+                err_msg: format!(
+                    "Bundle {} has no config of the terminal W{}_T{}",
+                    self.bundle_file_name, home_warehouse_id, this_terminal_id
+                ),
+            })?;
+        let path = bundle_dir.join(&self.bundle_file_name);
+        let read_err = |e: std::io::Error| Error {
+            err_msg: format!("Error reading bundle {:?}: {}", &path, e),
+        };
+        let mut file = File::open(&path).map_err(read_err)?;
+        file.seek(SeekFrom::Start(entry.offset)).map_err(read_err)?;
+        let mut doc = vec![0; entry.length as usize];
+        file.read_exact(&mut doc).map_err(read_err)?;
+        self.format.deserialize_cfg(&doc).map_err(|e| Error {
+            err_msg: format!(
+                "Config of the terminal W{}_T{} in the bundle {:?} has an incorrect format: {}",
+                home_warehouse_id, this_terminal_id, &path, e
+            ),
+        })
+    }
+}
+
+/// Read the config of a single terminal from a bundle, given the path of the bundle index
+pub fn read_bundled_cfg(
+    index_path: &Path,
+    home_warehouse_id: u32,
+    this_terminal_id: u32,
+) -> Result<TermControlCfg, Error> {
+    let index = BundleIndex::from_file(index_path)?;
+    let bundle_dir = index_path.parent().unwrap_or_else(|| Path::new(""));
+    index.read_cfg(bundle_dir, home_warehouse_id, this_terminal_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config with every optional field set on some transactions and left out on the others
+    fn full_cfg() -> TermControlCfg {
+        let tx = |typ: TransactionType, input: Option<TransactionInput>, start: Option<u64>| {
+            TransactionParams {
+                typ,
+                keying_time_ms: 3000,
+                think_time_ms: 12_500,
+                is_rbk: false,
+                input,
+                start_offset_ms: start,
+            }
+        };
+        TermControlCfg {
+            header: CfgHeader {
+                schema_version: CFG_SCHEMA_VERSION,
+                nurand: Some(NuRandConstants {
+                    c_last_load: 157,
+                    c_last: 223,
+                    c_id: 259,
+                    ol_i_id: 7911,
+                }),
+                time_scale: Some(TimeScale {
+                    think_time: 0.5,
+                    keying_time: 0.,
+                }),
+                non_compliance: vec!["Think times scaled by 0.5".to_string()],
+                arrival: Some(ArrivalSchedule {
+                    process: ArrivalProcess::Poisson,
+                    rate_tpm: 4.5,
+                }),
+            },
+            home_warehouse_id: 2,
+            this_terminal_id: 7,
+            district_id: Some(7),
+            start_delay_ms: 1500,
+            seed: Some(42),
+            think_time_distribution: vec![ThinkTimeDistribution {
+                typ: NewOrder,
+                mean_ms: 12_000,
+                max_ms: 120_000,
+            }],
+            transactions_to_run: vec![
+                tx(
+                    NewOrder,
+                    Some(TransactionInput::NewOrder(NewOrderInput {
+                        d_id: 7,
+                        c_id: 1021,
+                        order_lines: vec![OrderLineInput {
+                            ol_i_id: 4411,
+                            ol_supply_w_id: 1,
+                            ol_quantity: 5,
+                        }],
+                    })),
+                    Some(0),
+                ),
+                tx(
+                    Payment,
+                    Some(TransactionInput::Payment(PaymentInput {
+                        d_id: 7,
+                        c_w_id: 2,
+                        c_d_id: 3,
+                        customer: CustomerSelector::ByLastName("BARPRIPRES".to_string()),
+                        h_amount: 1234.56,
+                    })),
+                    Some(13_333),
+                ),
+                tx(
+                    OrderStatus,
+                    Some(TransactionInput::OrderStatus(OrderStatusInput {
+                        d_id: 7,
+                        customer: CustomerSelector::ById(17),
+                    })),
+                    None,
+                ),
+                tx(
+                    Delivery,
+                    Some(TransactionInput::Delivery(DeliveryInput {
+                        o_carrier_id: 4,
+                    })),
+                    None,
+                ),
+                tx(
+                    StockLevel,
+                    Some(TransactionInput::StockLevel(StockLevelInput {
+                        threshold: 15,
+                    })),
+                    None,
+                ),
+                tx(NewOrder, None, None),
+            ],
+        }
+    }
+
+    /// TOML is the fragile one: the header, the inputs and the other nested tables have to be written
+    /// after the plain fields of their parent table
+    #[test]
+    fn configs_round_trip_in_every_format() {
+        let cfg = full_cfg();
+        for format in &[
+            CfgFormat::Yaml,
+            CfgFormat::Json,
+            CfgFormat::Toml,
+            CfgFormat::MessagePack,
+        ] {
+            let doc = format
+                .serialize_cfg(&cfg)
+                .unwrap_or_else(|e| panic!("{}: {}", format, e));
+            assert_eq!(format.deserialize_cfg(&doc).unwrap(), cfg, "{}", format);
+        }
+        // The transaction type is written under its renamed key
+        let json = String::from_utf8(CfgFormat::Json.serialize_cfg(&cfg).unwrap()).unwrap();
+        assert!(json.contains(r#""type":"NewOrder""#), "{}", json);
+        assert!(!json.contains("typ\""));
+    }
+}
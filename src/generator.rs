@@ -1,85 +1,453 @@
 extern crate rand;
 
 use std::cmp::max;
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::time::Duration;
 
 use chrono::{DateTime, Local};
-use itertools::Itertools;
 use rand::distributions::Standard;
-use rand::rngs::{SmallRng, StdRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng, SeedableRng};
 use rand_distr::{Distribution, Poisson};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::cfg::*;
+use crate::launch::{log_files_glob, write_launch_files, LaunchParams};
+use crate::manifest::{file_sha256, suggested_steady_begin_offset_ms, RunManifest};
+use crate::naming::*;
+use crate::profile::*;
+use crate::rampup::RampUpPlan;
+use crate::sharding::*;
 use crate::terminal::*;
-use std::path::PathBuf;
+use crate::terminal_map::{resolve_terminal_counts, TerminalMap};
+use crate::tpcc::*;
+use crate::util::Error;
+use std::path::{Path, PathBuf};
 
-pub fn gen_cfg(warehouse_id_list: Vec<u32>, terminal_count: u32, transaction_count: u32) -> () {
-    if warehouse_id_list.len() == 0 {
-        panic!("Warehouse id list is empty");
-    }
-    if terminal_count == 0 {
-        panic!("Terminal count must be more than 0");
-    }
-    if transaction_count < 23 {
-        panic!("Minimum transaction count must be 23");
+/// Terminal configuration generation parameters.
+/// The ones added after the run manifest was introduced have defaults, so older manifests can be read:
+/// the terminal map, the arrival schedule, the time scale and the launch parameters
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GenCfgParams {
+    pub warehouse_id_list: Vec<u32>,
+    /// Terminals of every warehouse of the list not in the terminal map
+    pub terminal_count: Option<u32>,
+    /// Terminal count per warehouse, adds warehouses to the list and overrides the terminal count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal_map: Option<TerminalMap>,
+    /// Either a transaction count or a run duration defines the config length
+    pub transaction_count: Option<u32>,
+    pub duration: Option<Duration>,
+    /// Share of the duration generated on top of it, so slow terminals do not run out of transactions
+    pub margin_percent: u32,
+    pub seed: Option<u64>,
+    /// C constant of the C_LAST NURand used to populate the database
+    pub c_last_load: Option<u32>,
+    /// Run-time C constants, drawn at random if omitted
+    pub c_last: Option<u32>,
+    pub c_id: Option<u32>,
+    pub ol_i_id: Option<u32>,
+    pub profile: WorkloadProfile,
+    /// Open-loop arrival schedule, the transactions follow the keying and think times if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<ArrivalSchedule>,
+    /// Scale factors of the profile's keying and think times
+    #[serde(default)]
+    pub time_scale: TimeScale,
+    /// Generate even though the parameters break the TPC-C rules
+    pub non_compliant: bool,
+    pub ramp_up: RampUpPlan,
+    /// Serialization format of the per-terminal config files
+    pub format: CfgFormat,
+    /// Write every terminal config into a single bundle instead of a file per terminal
+    pub bundle: Option<BundleFormat>,
+    pub naming: OutputNaming,
+    /// Spread the terminals over driver hosts, a directory per host
+    pub driver_hosts: Option<DriverHosts>,
+    /// Shell script, systemd units or compose file starting the emulators
+    #[serde(default)]
+    pub launch: LaunchParams,
+}
+
+pub fn gen_cfg(params: GenCfgParams) {
+    let GenCfgParams {
+        warehouse_id_list,
+        terminal_count,
+        terminal_map,
+        seed,
+        ..
+    } = &params;
+    let terminal_counts =
+        resolve_terminal_counts(warehouse_id_list, *terminal_count, terminal_map.as_ref())
+            .unwrap_or_else(|e| panic!("{}", e.err_msg));
+    params
+        .time_scale
+        .check()
+        .unwrap_or_else(|e| panic!("{}", e.err_msg));
+    if let Some(arrival) = &params.arrival {
+        arrival.check().unwrap_or_else(|e| panic!("{}", e.err_msg));
     }
+    let profile = &params.profile.scaled(&params.time_scale);
+    let deck_size = profile.deck_size;
+    // Open-loop terminals run through a deck at the arrival rate, whatever the keying and think times
+    let deck_cycle_time_ms = match &params.arrival {
+        Some(arrival) => (deck_size as f64 * arrival.mean_interval_ms()).round() as u64,
+        None => profile.deck_cycle_time_ms(),
+    };
+    // Whole decks only, so the mix holds for every terminal
+    let deck_count = match (params.transaction_count, params.duration) {
+        (Some(transaction_count), _) => {
+            if transaction_count < deck_size {
+                panic!("Minimum transaction count must be {}", deck_size);
+            }
+            transaction_count.div_ceil(deck_size)
+        }
+        (None, Some(duration)) => {
+            duration_deck_count(deck_cycle_time_ms, duration, params.margin_percent)
+        }
+        (None, None) => panic!("Either transaction count or duration must be set"),
+    };
+    let mut violations = params.profile.violations();
+    violations.extend(params.time_scale.violations());
+    violations.extend(params.arrival.map(|arrival| {
+        format!(
+            "Open-loop {} arrivals at {} tpm per terminal replace the keying and think time pacing of Clause 5.2.5",
+            arrival.process, arrival.rate_tpm
+        )
+    }));
+    let distinct_counts: BTreeSet<u32> = terminal_counts.values().cloned().collect();
+    violations.extend(
+        distinct_counts
+            .into_iter()
+            .filter_map(terminal_count_violation),
+    );
+    check_compliance(&violations, params.non_compliant);
+    params
+        .launch
+        .validate(params.driver_hosts.is_some())
+        .unwrap_or_else(|e| panic!("{}", e.err_msg));
 
-    let wh_id_set: HashSet<_> = warehouse_id_list.clone().drain(..).collect(); // distinct values
+    // Warehouses without terminals are still populated, the remote transactions refer to them
+    let wh_ids: Vec<u32> = terminal_counts.keys().cloned().collect();
+    let active_wh_ids: Vec<u32> = terminal_counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(w, _)| *w)
+        .collect();
+    let seed = resolve_seed(*seed);
+    let nurand_c = NuRandConstants::new(
+        &mut StdRng::seed_from_u64(seed),
+        params.c_last_load,
+        params.c_last,
+        params.c_id,
+        params.ol_i_id,
+    )
+    .unwrap_or_else(|e| panic!("{}", e.err_msg));
+    println!(
+        "NURand C constants: C_LAST load {}, C_LAST {}, C_ID {}, OL_I_ID {}",
+        nurand_c.c_last_load, nurand_c.c_last, nurand_c.c_id, nurand_c.ol_i_id
+    );
     let now: DateTime<Local> = Local::now();
-    let ts = now.format("%Y%m%d_%H%M%S");
-    wh_id_set
+    let ts = now.format(TS_FORMAT).to_string();
+    let naming = &params.naming;
+    let name_vars = NameVars {
+        ts: &ts,
+        label: naming.label.as_deref(),
+        ..Default::default()
+    };
+    let cfg_file_dir = PathBuf::from(
+        render(
+            naming.output_dir.as_deref().unwrap_or(DEFAULT_CFG_DIR),
+            &name_vars,
+        )
+        .unwrap_or_else(|e| panic!("{}", e.err_msg)),
+    );
+    let name_template = match params.bundle {
+        None => naming.name_template.as_deref().unwrap_or(DEFAULT_CFG_NAME),
+        Some(_) => naming
+            .name_template
+            .as_deref()
+            .unwrap_or(DEFAULT_BUNDLE_NAME),
+    };
+    if params.bundle.is_none() {
+        require_placeholders(name_template, &["w", "t"])
+            .unwrap_or_else(|e| panic!("{}", e.err_msg));
+    }
+
+    let terms: Vec<(u32, u32)> = terminal_counts
         .iter()
-        .cartesian_product(1..terminal_count + 1)
-        .collect::<Vec<(&u32, u32)>>()
-        .par_iter_mut()
-        .for_each(|(w, t)| {
-            let mut tx_vec: Vec<TransactionParams> = Vec::new();
-            tx_breakdown(transaction_count)
-                .iter()
-                .for_each(|(typ, dflts)| {
-                    let mut rbk_count_left = dflts.rbk_count;
-                    for _ in 0..dflts.tx_count {
-                        tx_vec.push(TransactionParams {
-                            typ: typ.clone(),
-                            keying_time_ms: dflts.keying_time_ms,
-                            think_time_ms: dflts.think_time_ms,
-                            is_rbk: match rbk_count_left {
-                                0 => false,
-                                _ => {
-                                    rbk_count_left -= 1;
-                                    true
-                                }
-                            },
-                        });
-                    }
+        .flat_map(|(w, count)| (1..count + 1).map(move |t| (*w, t)))
+        .collect();
+    // Every driver host gets a contiguous range of the terminals and a directory of its own
+    let shards: Vec<(Option<&str>, Range<usize>)> = match &params.driver_hosts {
+        None => vec![(None, 0..terms.len())],
+        Some(DriverHosts(hosts)) => hosts
+            .iter()
+            .map(|host| Some(host.as_str()))
+            .zip(partition(
+                hosts.len(),
+                &active_wh_ids
+                    .iter()
+                    .map(|w| terminal_counts[w] as usize)
+                    .collect::<Vec<_>>(),
+            ))
+            .collect(),
+    };
+    let (term_total, wh_total) = (terms.len() as u64, active_wh_ids.len() as u64);
+    let gen_term_cfg = |term_idx: usize, w: u32, t: u32| -> TermControlCfg {
+        let wh_idx = active_wh_ids.binary_search(&w).unwrap() as u64;
+        let start_delay_ms =
+            params
+                .ramp_up
+                .start_delay_ms(term_idx as u64, term_total, wh_idx, wh_total);
+        let mut rng = term_rng(seed, w, t);
+        let mut tx_vec: Vec<TransactionParams> = Vec::new();
+        let tx_bkdwn = tx_breakdown(deck_size, profile);
+        let mut deck = new_deck(&tx_bkdwn);
+        for _ in 0..deck_count {
+            // Every deck is shuffled on its own, see TPC-C Clause 5.2.4.2
+            deck.shuffle(&mut rng);
+            deck.iter().for_each(|typ| {
+                let dflts = tx_bkdwn.get(typ).unwrap();
+                let think_time_ms = gen_think_time(&mut rng, dflts.think_time_mean_ms);
+                let is_rbk = rng.gen_range(1, 101) <= dflts.rbk_percent;
+                let input = gen_tx_input(&mut rng, typ, w, &wh_ids, is_rbk, &nurand_c);
+                tx_vec.push(TransactionParams {
+                    typ: typ.clone(),
+                    keying_time_ms: dflts.keying_time_ms,
+                    think_time_ms,
+                    is_rbk,
+                    input,
+                    start_offset_ms: None,
                 });
+            });
+        }
+        if let Some(arrival) = &params.arrival {
+            schedule_arrivals(
+                &mut tx_vec,
+                arrival,
+                &mut term_rng(seed ^ ARRIVAL_STREAM, w, t),
+            );
+        }
+
+        TermControlCfg {
+            header: CfgHeader {
+                schema_version: CFG_SCHEMA_VERSION,
+                nurand: Some(nurand_c.clone()),
+                time_scale: Some(params.time_scale).filter(|scale| scale.is_scaled()),
+                non_compliance: violations.clone(),
+                arrival: params.arrival,
+            },
+            home_warehouse_id: w,
+            this_terminal_id: t,
+            district_id: Some(district_of_terminal(t)),
+            start_delay_ms,
+            seed: Some(seed),
+            think_time_distribution: think_time_distribution(&tx_bkdwn),
+            transactions_to_run: tx_vec,
+        }
+    };
+
+    let write_cfg_file = |dir: &Path, term_idx: usize, w: u32, t: u32| {
+        let cfgz = gen_term_cfg(term_idx, w, t);
+        let doc = params
+            .format
+            .serialize_cfg(&cfgz)
+            .unwrap_or_else(|e| panic!("Error serializing cfg as {}: {}", params.format, e));
+        let cfg_file_name = format!(
+            "{}.{}",
+            render(
+                name_template,
+                &NameVars {
+                    w: Some(w),
+                    t: Some(t),
+                    ..name_vars
+                }
+            )
+            .unwrap(),
+            params.format.file_extension()
+        );
+        let cfg_file_path: PathBuf = [dir.to_str().unwrap(), &cfg_file_name].iter().collect();
+        fs::write(&cfg_file_path, &doc)
+            .unwrap_or_else(|e| panic!("Error writing cfg file {}: {}", &cfg_file_name, e));
+        cfg_file_name
+    };
 
-            let cfgz = TermControlCfg {
-                home_warehouse_id: w.clone(),
-                this_terminal_id: t.clone(),
-                transactions_to_run: tx_vec,
+    let (file_names, driver_hosts): (Vec<Vec<String>>, Vec<Option<HostAssignment>>) = shards
+        .iter()
+        .map(|(host, range)| {
+            let shard_dir = match host {
+                Some(host) => cfg_file_dir.join(host),
+                None => cfg_file_dir.clone(),
             };
-            let str = serde_yaml::to_string(&cfgz).expect("Unsupported configuration format");
-            let cfg_file_name = format!("{}_W{}_T{}.cfg", &ts, w, t);
-            let cfg_file_dir: PathBuf = ["term-config", &format!("{}", &ts)].iter().collect();
-            let cfg_file_path: PathBuf = [cfg_file_dir.to_str().unwrap(), &cfg_file_name]
-                .iter()
+            fs::create_dir_all(&shard_dir).unwrap_or_else(|e| {
+                panic!(
+                    "Error creating terminal configuration directory {:?}: {}",
+                    &shard_dir, e
+                )
+            });
+            let shard_terms = &terms[range.clone()];
+            let mut file_names: Vec<String> = match params.bundle {
+                None => shard_terms
+                    .par_iter()
+                    .enumerate()
+                    .map(|(idx, (w, t))| write_cfg_file(&shard_dir, range.start + idx, *w, *t))
+                    .collect(),
+                Some(format) => {
+                    let bundle_name = render(name_template, &name_vars)
+                        .unwrap_or_else(|e| panic!("{}", e.err_msg));
+                    write_bundle(
+                        &shard_dir,
+                        &bundle_name,
+                        format,
+                        shard_terms,
+                        |idx, w, t| gen_term_cfg(range.start + idx, w, t),
+                    )
+                }
+            };
+            let launch_files = match params.bundle {
+                None => write_launch_files(
+                    &shard_dir,
+                    *host,
+                    &params.launch,
+                    &name_vars,
+                    shard_terms,
+                    &file_names,
+                    None,
+                ),
+                // The bundle index comes after the bundle
+                Some(_) => write_launch_files(
+                    &shard_dir,
+                    *host,
+                    &params.launch,
+                    &name_vars,
+                    shard_terms,
+                    &[],
+                    Some(&file_names[1]),
+                ),
+            };
+            file_names.extend(launch_files);
+            // File paths relative to the manifest
+            let file_names = file_names
+                .into_iter()
+                .map(|name| match host {
+                    Some(host) => format!("{}/{}", host, name),
+                    None => name,
+                })
                 .collect();
-            fs::create_dir_all(&cfg_file_dir).expect(&format!(
-                "Error creating terminal configuration directory {:?}",
-                &cfg_file_dir
-            ));
-            fs::write(&cfg_file_path, &str)
-                .expect(&format!("Error writing cfg file {}", &cfg_file_name));
-        });
+            (
+                file_names,
+                host.map(|host| host_assignment(host, shard_terms)),
+            )
+        })
+        .unzip();
+    let files = file_names
+        .concat()
+        .into_par_iter()
+        .map(|name| {
+            let checksum = file_sha256(&cfg_file_dir.join(&name))
+                .unwrap_or_else(|e| panic!("Error reading generated file {}: {}", &name, e));
+            (name, checksum)
+        })
+        .collect();
+    if !params.launch.launch.is_empty() {
+        println!(
+            "Launch artifacts written, the emulator logs are expected at \"{}\"",
+            log_files_glob(&cfg_file_dir, params.driver_hosts.is_some())
+        );
+    }
+
+    RunManifest {
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        seed,
+        terminal_count: terms.len() as u64,
+        terminals_per_warehouse: terminal_counts.clone(),
+        ramp_up: params.ramp_up.clone(),
+        non_compliance: violations,
+        last_start_delay_ms: Some(params.ramp_up.last_start_delay_ms(term_total, wh_total)),
+        suggested_steady_begin_offset_ms: suggested_steady_begin_offset_ms(
+            &params.ramp_up,
+            term_total,
+            wh_total,
+            deck_cycle_time_ms,
+        ),
+        driver_hosts: driver_hosts.into_iter().flatten().collect(),
+        files,
+        params,
+    }
+    .write(&cfg_file_dir);
+}
+
+/// Number of terminal configs generated in parallel and held in memory before they are appended to a bundle
+const BUNDLE_CHUNK_SIZE: usize = 256;
+
+/// Write every terminal config into a single bundle file and its index of byte offsets
+fn write_bundle<F>(
+    cfg_file_dir: &Path,
+    bundle_name: &str,
+    format: BundleFormat,
+    terms: &[(u32, u32)],
+    gen_term_cfg: F,
+) -> Vec<String>
+where
+    F: Fn(usize, u32, u32) -> TermControlCfg + Sync,
+{
+    let bundle_file_name = format!("{}.{}", bundle_name, format.file_extension());
+    let bundle_path = cfg_file_dir.join(&bundle_file_name);
+    let mut wtr = BufWriter::new(
+        File::create(&bundle_path)
+            .unwrap_or_else(|e| panic!("Error creating bundle file {:?}: {}", &bundle_path, e)),
+    );
+    let mut entries: Vec<BundleEntry> = Vec::with_capacity(terms.len());
+    let mut offset = 0;
+    for (chunk_idx, chunk) in terms.chunks(BUNDLE_CHUNK_SIZE).enumerate() {
+        let docs: Vec<Vec<u8>> = chunk
+            .par_iter()
+            .enumerate()
+            .map(|(idx, (w, t))| {
+                format
+                    .serialize_cfg(&gen_term_cfg(chunk_idx * BUNDLE_CHUNK_SIZE + idx, *w, *t))
+                    .unwrap_or_else(|e| panic!("Error serializing cfg as {}: {}", format, e))
+            })
+            .collect();
+        for ((w, t), doc) in chunk.iter().zip(docs) {
+            wtr.write_all(&doc)
+                .unwrap_or_else(|e| panic!("Error writing bundle file {:?}: {}", &bundle_path, e));
+            entries.push(BundleEntry {
+                home_warehouse_id: *w,
+                this_terminal_id: *t,
+                offset,
+                length: doc.len() as u64,
+            });
+            offset += doc.len() as u64;
+        }
+    }
+    wtr.flush()
+        .unwrap_or_else(|e| panic!("Error writing bundle file {:?}: {}", &bundle_path, e));
+
+    let index_file_name = format!("{}.index.yaml", bundle_name);
+    BundleIndex {
+        format,
+        bundle_file_name: bundle_file_name.clone(),
+        terminals: entries,
+    }
+    .write(&cfg_file_dir.join(&index_file_name));
+    vec![bundle_file_name, index_file_name]
 }
 
 /// Generate sample log files with fixed deck size and configurable terminal count and iteration count
-pub fn gen_sample_data(terminal_count: u32, iteration_count: u32) -> () {
+pub fn gen_sample_data(
+    terminal_count: u32,
+    iteration_count: u32,
+    seed: Option<u64>,
+    start_time_ms: Option<u64>,
+    naming: OutputNaming,
+) {
     if terminal_count == 0 {
         panic!("Terminal count must be more than 0");
     }
@@ -90,46 +458,69 @@ pub fn gen_sample_data(terminal_count: u32, iteration_count: u32) -> () {
     const TRANSACTION_COUNT: u32 = 100;
 
     let now: DateTime<Local> = Local::now();
-    let start_ts = now.format("%Y%m%d_%H%M%S");
+    let start_ts = now.format(TS_FORMAT).to_string();
+    let name_vars = NameVars {
+        ts: &start_ts,
+        label: naming.label.as_deref(),
+        ..Default::default()
+    };
+    let name_template = naming
+        .name_template
+        .as_deref()
+        .unwrap_or(DEFAULT_SAMPLE_LOG_NAME);
+    require_placeholders(name_template, &["t"]).unwrap_or_else(|e| panic!("{}", e.err_msg));
+    let seed = resolve_seed(seed);
+    let start_time_ms = start_time_ms.unwrap_or(now.timestamp_millis() as u64);
+
+    let log_file_dir = PathBuf::from(
+        render(
+            naming
+                .output_dir
+                .as_deref()
+                .unwrap_or(DEFAULT_SAMPLE_LOG_DIR),
+            &name_vars,
+        )
+        .unwrap_or_else(|e| panic!("{}", e.err_msg)),
+    );
+    fs::create_dir_all(&log_file_dir).unwrap_or_else(|e| {
+        panic!(
+            "Error creating sample logs directory {:?}: {}",
+            &log_file_dir, e
+        )
+    });
+    fs::write(log_file_dir.join(SEED_FILE_NAME), seed.to_string())
+        .unwrap_or_else(|e| panic!("Error writing seed file in {:?}: {}", &log_file_dir, e));
 
     (0..terminal_count)
         .collect::<Vec<u32>>()
         .par_iter_mut()
         .for_each(|t| {
-            let log_file_name = format!("{}_T{}.csv", start_ts, t);
-
-            let log_file_dir: PathBuf = ["sample-logs", &format!("{}", &start_ts)].iter().collect();
+            let log_file_name = format!(
+                "{}.csv",
+                render(
+                    name_template,
+                    &NameVars {
+                        t: Some(*t),
+                        ..name_vars
+                    }
+                )
+                .unwrap_or_else(|e| panic!("{}", e.err_msg))
+            );
             let log_file_path: PathBuf = [log_file_dir.to_str().unwrap(), &log_file_name]
                 .iter()
                 .collect();
-            fs::create_dir_all(&log_file_dir).expect(&format!(
-                "Error creating sample logs directory {:?}",
-                &log_file_dir
-            ));
 
             let mut wtr = csv::Writer::from_path(&log_file_path).unwrap();
 
-            let tx_bkdwn = tx_breakdown(TRANSACTION_COUNT);
+            let tx_bkdwn = tx_breakdown(TRANSACTION_COUNT, &WorkloadProfile::default());
 
-            // Initialize deck with the generated distribution of transaction types
-            let mut deck: Vec<TransactionType> = tx_bkdwn
-                .iter()
-                .map(|(tx_type, tx_def)| {
-                    (0..tx_def.tx_count)
-                        .collect::<Vec<u32>>()
-                        .iter()
-                        .map(|_| tx_type.clone())
-                        .collect()
-                })
-                .flat_map(|t_t: Vec<TransactionType>| t_t)
-                .collect();
+            let mut deck = new_deck(&tx_bkdwn);
             let deck_slice: &mut [TransactionType] = &mut deck;
 
-            let mut rng = thread_rng();
-            let mut small_rng = SmallRng::from_entropy();
+            let mut rng = term_rng(seed, 0, *t);
             let poi = Poisson::new(2.0).unwrap();
 
-            let mut term_running_time = Local::now().timestamp_millis() as u64;
+            let mut term_running_time = start_time_ms;
 
             (0..iteration_count)
                 .collect::<Vec<u32>>()
@@ -137,106 +528,695 @@ pub fn gen_sample_data(terminal_count: u32, iteration_count: u32) -> () {
                 .for_each(|_| {
                     deck_slice.shuffle(&mut rng);
 
-                    deck_slice
-                        .iter()
-                        .cloned()
-                        .for_each(|tx_type: TransactionType| {
-                            let tx_def = tx_bkdwn.get(&tx_type).unwrap();
-
-                            let keying_time = tx_def.keying_time_ms;
-
-                            let mut tx_rt_smpl_f: f64 = poi.sample(&mut small_rng);
-                            tx_rt_smpl_f += 1.;
-                            let rt_smpl_f: f64 = tx_rt_smpl_f * small_rng.gen_range(1.05, 1.15);
-                            let tx_rt_smpl = (tx_rt_smpl_f * 1000.) as u32;
-                            let rt_smpl = (rt_smpl_f * 1000.) as u32;
-
-                            let think_time = tx_def.think_time_ms;
-
-                            wtr.serialize(TermLogRecord {
-                                time_started: term_running_time,
-                                typ: tx_type.clone(),
-                                running_time: rt_smpl,
-                                tx_running_time: tx_rt_smpl,
-                                think_time_ms: think_time,
-                                is_rbk: false,
-                            })
-                            .expect(&format!(
-                                "Error writing sample record to the file {}",
-                                &log_file_name
-                            ));
-
-                            term_running_time += (keying_time + rt_smpl + think_time) as u64;
+                    deck_slice.iter().for_each(|tx_type: &TransactionType| {
+                        let tx_def = tx_bkdwn.get(tx_type).unwrap();
+
+                        let keying_time = tx_def.keying_time_ms;
+
+                        let mut tx_rt_smpl_f: f64 = poi.sample(&mut rng);
+                        tx_rt_smpl_f += 1.;
+                        let rt_smpl_f: f64 = tx_rt_smpl_f * rng.gen_range(1.05, 1.15);
+                        let tx_rt_smpl = (tx_rt_smpl_f * 1000.) as u32;
+                        let rt_smpl = (rt_smpl_f * 1000.) as u32;
+
+                        let think_time = gen_think_time(&mut rng, tx_def.think_time_mean_ms);
+
+                        wtr.serialize(TermLogRecord {
+                            time_started: term_running_time,
+                            typ: tx_type.clone(),
+                            running_time: rt_smpl,
+                            tx_running_time: tx_rt_smpl,
+                            think_time_ms: think_time,
+                            is_rbk: false,
+                        })
+                        .unwrap_or_else(|e| {
+                            panic!(
+                                "Error writing sample record to the file {}: {}",
+                                &log_file_name, e
+                            )
                         });
+
+                        term_running_time += (keying_time + rt_smpl + think_time) as u64;
+                    });
                 });
         });
 }
 
-struct TransactionDefaults {
-    tx_count: u32,
-    keying_time_ms: u32,
-    think_time_ms: u32,
-    rbk_count: u32,
-}
-
-fn tx_breakdown(transaction_count: u32) -> HashMap<TransactionType, TransactionDefaults> {
-    let mut map = HashMap::new();
-    let tc_f64 = transaction_count as f64;
-    map.insert(
-        TransactionType::Payment,
-        TransactionDefaults {
-            tx_count: fraction_non_zero(tc_f64, 0.44),
-            keying_time_ms: 3_000,
-            think_time_ms: gen_think_time(12),
-            rbk_count: 0,
-        },
-    );
-    map.insert(
-        TransactionType::OrderStatus,
-        TransactionDefaults {
-            tx_count: fraction_non_zero(tc_f64, 0.04),
-            keying_time_ms: 2_000,
-            think_time_ms: gen_think_time(10),
-            rbk_count: 0,
-        },
-    );
-    map.insert(
-        TransactionType::Delivery,
-        TransactionDefaults {
-            tx_count: fraction_non_zero(tc_f64, 0.04),
-            keying_time_ms: 2_000,
-            think_time_ms: gen_think_time(5),
-            rbk_count: 0,
-        },
-    );
-    map.insert(
-        TransactionType::StockLevel,
-        TransactionDefaults {
-            tx_count: fraction_non_zero(tc_f64, 0.04),
-            keying_time_ms: 2_000,
-            think_time_ms: gen_think_time(5),
-            rbk_count: 0,
-        },
+/// Number of decks covering the duration and the margin, estimated from the expected deck cycle time
+fn duration_deck_count(deck_cycle_time_ms: u64, duration: Duration, margin_percent: u32) -> u32 {
+    if deck_cycle_time_ms == 0 {
+        panic!("Deck cycle time is zero, use the transaction count instead of the duration");
+    }
+    let target_ms = duration.as_millis() as u64 * (100 + margin_percent as u64) / 100;
+    let deck_count = max(1, target_ms.div_ceil(deck_cycle_time_ms)) as u32;
+    println!(
+        "Expected deck cycle time {}, generating {} decks per terminal",
+        humantime::format_duration(Duration::from_millis(deck_cycle_time_ms)),
+        deck_count
     );
-    let new_order_count = transaction_count - map.iter().map(|(_, v)| v.tx_count).sum::<u32>();
-    map.insert(
-        TransactionType::NewOrder,
-        TransactionDefaults {
-            tx_count: new_order_count,
-            keying_time_ms: 18_000,
-            think_time_ms: gen_think_time(12),
-            rbk_count: fraction_non_zero(new_order_count as f64, 0.01),
-        },
+    deck_count
+}
+
+/// Refuse to generate a non-compliant run unless it is requested explicitly
+fn check_compliance(violations: &[String], non_compliant: bool) {
+    if violations.is_empty() {
+        return;
+    }
+    let msg = violations.join("\n");
+    if non_compliant {
+        println!("Generating a non-compliant run:\n{}", msg);
+    } else {
+        panic!(
+            "Parameters break the TPC-C rules, pass --non-compliant to generate anyway:\n{}",
+            msg
+        );
+    }
+}
+
+/// Draw the terminal input data of a single transaction
+fn gen_tx_input<R: Rng>(
+    rng: &mut R,
+    typ: &TransactionType,
+    w_id: u32,
+    wh_ids: &[u32],
+    is_rbk: bool,
+    nurand_c: &NuRandConstants,
+) -> Option<TransactionInput> {
+    match typ {
+        TransactionType::NewOrder => Some(TransactionInput::NewOrder(gen_new_order_input(
+            rng, w_id, wh_ids, is_rbk, nurand_c,
+        ))),
+        TransactionType::Payment => Some(TransactionInput::Payment(gen_payment_input(
+            rng, w_id, wh_ids, nurand_c,
+        ))),
+        TransactionType::OrderStatus => Some(TransactionInput::OrderStatus(OrderStatusInput {
+            d_id: rand_range(rng, 1, DISTRICTS_PER_WAREHOUSE),
+            customer: gen_customer_selector(rng, nurand_c),
+        })),
+        TransactionType::Delivery => Some(TransactionInput::Delivery(DeliveryInput {
+            o_carrier_id: rand_range(rng, 1, MAX_CARRIER_ID),
+        })),
+        TransactionType::StockLevel => Some(TransactionInput::StockLevel(StockLevelInput {
+            threshold: rand_range(rng, MIN_STOCK_THRESHOLD, MAX_STOCK_THRESHOLD),
+        })),
+    }
+}
+
+/// NewOrder input data, see TPC-C Clause 2.4.1
+fn gen_new_order_input<R: Rng>(
+    rng: &mut R,
+    w_id: u32,
+    wh_ids: &[u32],
+    is_rbk: bool,
+    nurand_c: &NuRandConstants,
+) -> NewOrderInput {
+    let d_id = rand_range(rng, 1, DISTRICTS_PER_WAREHOUSE);
+    let c_id = nurand(rng, NURAND_A_C_ID, 1, CUSTOMERS_PER_DISTRICT, nurand_c.c_id);
+    let ol_cnt = rand_range(rng, MIN_ORDER_LINE_COUNT, MAX_ORDER_LINE_COUNT);
+    let order_lines = (1..ol_cnt + 1)
+        .map(|ol_number| {
+            let ol_i_id = if is_rbk && ol_number == ol_cnt {
+                // The last order line of a rolled back order refers to an unused item
+                UNUSED_ITEM_ID
+            } else {
+                nurand(rng, NURAND_A_OL_I_ID, 1, ITEM_COUNT, nurand_c.ol_i_id)
+            };
+            let ol_supply_w_id = if rand_range(rng, 1, 100) > 1 {
+                w_id
+            } else {
+                remote_warehouse_id(rng, w_id, wh_ids)
+            };
+            OrderLineInput {
+                ol_i_id,
+                ol_supply_w_id,
+                ol_quantity: rand_range(rng, 1, MAX_ORDER_LINE_QUANTITY),
+            }
+        })
+        .collect();
+
+    NewOrderInput {
+        d_id,
+        c_id,
+        order_lines,
+    }
+}
+
+/// Payment input data, see TPC-C Clause 2.5.1
+fn gen_payment_input<R: Rng>(
+    rng: &mut R,
+    w_id: u32,
+    wh_ids: &[u32],
+    nurand_c: &NuRandConstants,
+) -> PaymentInput {
+    let d_id = rand_range(rng, 1, DISTRICTS_PER_WAREHOUSE);
+    // 85% of the customers belong to the home warehouse and district, 15% are remote
+    let (c_w_id, c_d_id) = if rand_range(rng, 1, 100) <= 85 {
+        (w_id, d_id)
+    } else {
+        (
+            remote_warehouse_id(rng, w_id, wh_ids),
+            rand_range(rng, 1, DISTRICTS_PER_WAREHOUSE),
+        )
+    };
+    let customer = gen_customer_selector(rng, nurand_c);
+    let h_amount = rand_range(rng, MIN_H_AMOUNT_CENTS, MAX_H_AMOUNT_CENTS) as f64 / 100.;
+
+    PaymentInput {
+        d_id,
+        c_w_id,
+        c_d_id,
+        customer,
+        h_amount,
+    }
+}
+
+/// 60% of the customers are selected by the last name and 40% by the id, see TPC-C Clause 2.5.1.2
+fn gen_customer_selector<R: Rng>(rng: &mut R, nurand_c: &NuRandConstants) -> CustomerSelector {
+    if rand_range(rng, 1, 100) <= 60 {
+        CustomerSelector::ByLastName(last_name(nurand(
+            rng,
+            NURAND_A_C_LAST,
+            0,
+            999,
+            nurand_c.c_last,
+        )))
+    } else {
+        CustomerSelector::ById(nurand(
+            rng,
+            NURAND_A_C_ID,
+            1,
+            CUSTOMERS_PER_DISTRICT,
+            nurand_c.c_id,
+        ))
+    }
+}
+
+/// Random warehouse other than the home one. Falls back to the home warehouse when it is the only one
+fn remote_warehouse_id<R: Rng>(rng: &mut R, w_id: u32, wh_ids: &[u32]) -> u32 {
+    let remote_ids: Vec<&u32> = wh_ids.iter().filter(|id| **id != w_id).collect();
+    match remote_ids.choose(rng) {
+        Some(id) => **id,
+        None => w_id,
+    }
+}
+
+/// Name of the file holding the seed of a generated sample logs set
+pub const SEED_FILE_NAME: &str = "seed";
+
+/// Largest seed accepted. TOML and the emulators with signed 64-bit integers cannot hold the larger ones
+pub const MAX_SEED: u64 = i64::MAX as u64;
+
+/// Seed given on the command line, up to `MAX_SEED`
+pub fn parse_seed(s: &str) -> Result<u64, Error> {
+    let seed = s.parse::<u64>().map_err(|_| Error {
+        err_msg: format!("Seed has an incorrect format '{}'", s),
+    })?;
+    if seed > MAX_SEED {
+        return Err(Error {
+            err_msg: format!("Seed {} is too large, the maximum is {}", seed, MAX_SEED),
+        });
+    }
+    Ok(seed)
+}
+
+/// Use the seed provided or draw a fresh one, printing it out so the run can be reproduced.
+/// Fresh seeds stay below `MAX_SEED` as well
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    let seed = seed.unwrap_or_else(|| thread_rng().gen_range(0, MAX_SEED));
+    println!("Seed {}", seed);
+    seed
+}
+
+/// Random number generator of a single terminal. The seed is derived from the global seed and
+/// the (warehouse, terminal) pair only, so the output does not depend on the thread schedule
+pub fn term_rng(seed: u64, warehouse_id: u32, terminal_id: u32) -> StdRng {
+    // SplitMix64 finalizer spreads the neighbouring pairs apart
+    let mut z = seed.wrapping_add(
+        (((warehouse_id as u64) << 32) | terminal_id as u64).wrapping_mul(GOLDEN_GAMMA),
     );
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    StdRng::seed_from_u64(z ^ (z >> 31))
+}
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Salt of the seed the arrivals are drawn with, so a terminal's transactions do not depend on the schedule
+const ARRIVAL_STREAM: u64 = 0xA076_1D64_78BD_642F;
+
+/// Set the intended start offsets of the transactions, the first one starts right after the start delay
+fn schedule_arrivals<R: Rng>(
+    tx_vec: &mut [TransactionParams],
+    arrival: &ArrivalSchedule,
+    rng: &mut R,
+) {
+    let mean_interval_ms = arrival.mean_interval_ms();
+    let mut offset_ms: f64 = 0.;
+    tx_vec.iter_mut().for_each(|tx| {
+        tx.start_offset_ms = Some(offset_ms.round() as u64);
+        offset_ms += match arrival.process {
+            ArrivalProcess::Fixed => mean_interval_ms,
+            // Uncapped, a cap would lower the arrival rate
+            ArrivalProcess::Poisson => gen_neg_exp(rng, mean_interval_ms, f64::INFINITY),
+        };
+    });
+}
+
+/// Cards of a transaction type in a deck and its times
+pub struct TransactionDefaults {
+    pub tx_count: u32,
+    pub keying_time_ms: u32,
+    pub think_time_mean_ms: u32,
+    /// Chance of a rollback drawn for every single transaction, see TPC-C Clause 2.4.1.4
+    pub rbk_percent: u32,
+}
+
+/// Think time upper bound as a multiple of its mean, see TPC-C Clause 5.2.5.4
+pub const THINK_TIME_MAX_FACTOR: u32 = 10;
 
-    map
+pub fn tx_breakdown(
+    transaction_count: u32,
+    profile: &WorkloadProfile,
+) -> HashMap<TransactionType, TransactionDefaults> {
+    TransactionType::iter()
+        .map(|tx_type| {
+            let p = profile.get(tx_type);
+            (
+                tx_type.clone(),
+                TransactionDefaults {
+                    tx_count: profile.card_count(tx_type, transaction_count),
+                    keying_time_ms: p.keying_time_ms,
+                    think_time_mean_ms: p.think_time_mean_ms,
+                    rbk_percent: p.rbk_percent,
+                },
+            )
+        })
+        .collect()
 }
 
-fn fraction_non_zero(base: f64, fraction: f64) -> u32 {
-    max(1, (base * fraction) as u32)
+/// Initialize a deck with the generated distribution of transaction types
+fn new_deck(tx_bkdwn: &HashMap<TransactionType, TransactionDefaults>) -> Vec<TransactionType> {
+    TransactionType::iter()
+        .flat_map(|tx_type| {
+            let tx_count = tx_bkdwn.get(tx_type).unwrap().tx_count;
+            (0..tx_count).map(move |_| tx_type.clone())
+        })
+        .collect()
 }
 
-fn gen_think_time(mean_time_s: u32) -> u32 {
-    let dstr: f64 = StdRng::from_entropy().sample(Standard);
-    (-dstr.ln() * (mean_time_s as f64) * 1000.0) as u32
+/// Draw a think time from the negative exponential distribution truncated at
+/// `THINK_TIME_MAX_FACTOR` times the mean
+fn gen_think_time<R: Rng>(rng: &mut R, mean_time_ms: u32) -> u32 {
+    gen_neg_exp(
+        rng,
+        mean_time_ms as f64,
+        (mean_time_ms * THINK_TIME_MAX_FACTOR) as f64,
+    ) as u32
+}
+
+/// Draw from the negative exponential distribution of the mean given, truncated at `cap`
+fn gen_neg_exp<R: Rng>(rng: &mut R, mean: f64, cap: f64) -> f64 {
+    // `Standard` samples from [0, 1), flip it to (0, 1] to keep the logarithm finite
+    let dstr: f64 = 1. - rng.sample::<f64, _>(Standard);
+    (-dstr.ln() * mean).min(cap)
+}
+
+fn think_time_distribution(
+    tx_bkdwn: &HashMap<TransactionType, TransactionDefaults>,
+) -> Vec<ThinkTimeDistribution> {
+    TransactionType::iter()
+        .map(|tx_type| {
+            let mean_ms = tx_bkdwn.get(tx_type).unwrap().think_time_mean_ms;
+            ThinkTimeDistribution {
+                typ: tx_type.clone(),
+                mean_ms,
+                max_ms: mean_ms * THINK_TIME_MAX_FACTOR,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launch::{LaunchKind, LAUNCH_SCRIPT_NAME};
+    use crate::manifest::MANIFEST_FILE_NAME;
+    use crate::rampup::RampUpMode;
+    use crate::terminal_map::parse_terminal_map;
+    use std::collections::HashSet;
+
+    fn gen_params(out_dir: &Path, seed: u64) -> GenCfgParams {
+        GenCfgParams {
+            warehouse_id_list: vec![1, 2, 3],
+            terminal_count: Some(DISTRICTS_PER_WAREHOUSE),
+            terminal_map: None,
+            transaction_count: Some(2 * DECK_SIZE),
+            duration: None,
+            margin_percent: 10,
+            seed: Some(seed),
+            c_last_load: None,
+            c_last: None,
+            c_id: None,
+            ol_i_id: None,
+            profile: WorkloadProfile::default(),
+            arrival: None,
+            time_scale: TimeScale::default(),
+            non_compliant: false,
+            ramp_up: RampUpPlan::new(RampUpMode::Linear, Duration::from_secs(30), 1),
+            format: CfgFormat::Yaml,
+            bundle: None,
+            naming: OutputNaming {
+                output_dir: Some(out_dir.to_str().unwrap().to_string()),
+                name_template: Some("W{w}_T{t}".to_string()),
+                label: None,
+            },
+            driver_hosts: None,
+            launch: LaunchParams::default(),
+        }
+    }
+
+    /// Every generated file but the manifest, which records the output directory
+    fn generated_files(dir: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files: Vec<(String, Vec<u8>)> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap() != MANIFEST_FILE_NAME)
+            .map(|path| {
+                (
+                    path.file_name().unwrap().to_str().unwrap().to_string(),
+                    fs::read(&path).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn same_seed_generates_identical_configs() {
+        let (first, second, other) = (
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+        );
+        gen_cfg(gen_params(first.path(), 42));
+        gen_cfg(gen_params(second.path(), 42));
+        gen_cfg(gen_params(other.path(), 43));
+
+        let first_files = generated_files(first.path());
+        assert_eq!(first_files.len(), 3 * DISTRICTS_PER_WAREHOUSE as usize);
+        assert!(first_files == generated_files(second.path()));
+        let other_files = generated_files(other.path());
+        assert_eq!(
+            first_files.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            other_files.iter().map(|(name, _)| name).collect::<Vec<_>>()
+        );
+        assert!(first_files
+            .iter()
+            .zip(&other_files)
+            .all(|((_, first), (_, other))| first != other));
+    }
+
+    #[test]
+    fn terminals_get_distinct_streams() {
+        let first_draws: HashSet<u64> = (1..=50)
+            .flat_map(|w| (1..=20).map(move |t| term_rng(42, w, t).gen::<u64>()))
+            .collect();
+        assert_eq!(first_draws.len(), 50 * 20);
+        // Swapped ids and neighbouring seeds do not share a stream either
+        assert_ne!(
+            term_rng(42, 1, 2).gen::<u64>(),
+            term_rng(42, 2, 1).gen::<u64>()
+        );
+        assert_ne!(
+            term_rng(42, 1, 1).gen::<u64>(),
+            term_rng(43, 1, 1).gen::<u64>()
+        );
+        assert_ne!(
+            term_rng(42, 0, 1).gen::<u64>(),
+            term_rng(42, 1, 0).gen::<u64>()
+        );
+    }
+
+    #[test]
+    fn launched_runs_quote_values_and_verify_with_logs() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut params = gen_params(out_dir.path(), 42);
+        params.naming.name_template = Some("W{w}_T{t} {label}".to_string());
+        params.naming.label = Some("it's run 1".to_string());
+        params.driver_hosts = Some(parse_driver_hosts("2").unwrap());
+        params.launch = LaunchParams {
+            launch: vec![LaunchKind::Shell],
+            launch_command: Some("emulator --cfg {cfg} --log {log} --label {label}".to_string()),
+            ..LaunchParams::default()
+        };
+        gen_cfg(params);
+
+        let script =
+            fs::read_to_string(out_dir.path().join("host1").join(LAUNCH_SCRIPT_NAME)).unwrap();
+        assert!(
+            script.contains("emulator --cfg 'W1_T1 it'\\''s run 1.cfg' --log logs/W1_T1.csv --label 'it'\\''s run 1' &"),
+            "{}",
+            script
+        );
+
+        let manifest = RunManifest::from_file(&out_dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        for log in &[
+            "host1/logs/W1_T1.csv",
+            "host2/logs/W3_T10.csv",
+            "logs/all.csv",
+        ] {
+            let path = out_dir.path().join(log);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "time_started\n").unwrap();
+        }
+        fs::write(out_dir.path().join("host1").join("notes.txt"), "").unwrap();
+        let report = manifest.verify(out_dir.path()).unwrap();
+        assert!(report.missing.is_empty() && report.modified.is_empty());
+        assert_eq!(report.extra, vec!["host1/notes.txt".to_string()]);
+    }
+
+    /// Legacy config of a terminal with `t` transactions, so the documents of a bundle differ in length
+    /// and grow on migration
+    fn legacy_term_cfg(idx: usize, w: u32, t: u32) -> TermControlCfg {
+        TermControlCfg {
+            header: CfgHeader::default(),
+            home_warehouse_id: w,
+            this_terminal_id: t,
+            district_id: None,
+            start_delay_ms: idx as u64 * 1000,
+            seed: None,
+            think_time_distribution: Vec::new(),
+            transactions_to_run: (0..t)
+                .map(|i| TransactionParams {
+                    typ: TransactionType::Payment,
+                    keying_time_ms: 3000,
+                    think_time_ms: 12_000 + i,
+                    is_rbk: false,
+                    input: None,
+                    start_offset_ms: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Checks the terminal W2_T5 read back by its offset and that the documents cover the bundle end to end
+    fn check_bundle(dir: &Path, format: BundleFormat, schema_version: u32) {
+        let index_path = dir.join("bundle.index.yaml");
+        let cfg = read_bundled_cfg(&index_path, 2, 5).unwrap();
+        assert_eq!(cfg.header.schema_version, schema_version, "{}", format);
+        assert_eq!((cfg.home_warehouse_id, cfg.this_terminal_id), (2, 5));
+        assert_eq!(cfg.start_delay_ms, 14_000);
+        assert_eq!(cfg.transactions_to_run.len(), 5);
+        assert_eq!(cfg.transactions_to_run[4].think_time_ms, 12_004);
+
+        let index = BundleIndex::from_file(&index_path).unwrap();
+        assert_eq!(index.terminals.len(), 30);
+        let end = index.terminals.iter().fold(0, |offset, entry| {
+            assert_eq!(entry.offset, offset, "{}", format);
+            offset + entry.length
+        });
+        assert_eq!(
+            end,
+            fs::metadata(dir.join(&index.bundle_file_name))
+                .unwrap()
+                .len()
+        );
+        assert!(read_bundled_cfg(&index_path, 4, 1).is_err());
+    }
+
+    #[test]
+    fn bundled_configs_are_read_by_offset_before_and_after_migration() {
+        let terms: Vec<(u32, u32)> = (1..=3)
+            .flat_map(|w| (1..=DISTRICTS_PER_WAREHOUSE).map(move |t| (w, t)))
+            .collect();
+        for format in &[BundleFormat::Yaml, BundleFormat::JsonLines] {
+            let dir = tempfile::tempdir().unwrap();
+            write_bundle(dir.path(), "bundle", *format, &terms, legacy_term_cfg);
+            check_bundle(dir.path(), *format, LEGACY_CFG_SCHEMA_VERSION);
+            let length = BundleIndex::from_file(&dir.path().join("bundle.index.yaml"))
+                .unwrap()
+                .terminals[14]
+                .length;
+
+            crate::migrate::migrate_cfg_dir(dir.path());
+            check_bundle(dir.path(), *format, CFG_SCHEMA_VERSION);
+            let index = BundleIndex::from_file(&dir.path().join("bundle.index.yaml")).unwrap();
+            assert!(index.terminals[14].length > length);
+            let cfg = read_bundled_cfg(&dir.path().join("bundle.index.yaml"), 2, 5).unwrap();
+            assert_eq!(cfg.district_id, Some(5));
+        }
+    }
+
+    /// Start offsets of `count` transactions scheduled at 6 tpm, 10 seconds apart on average
+    fn arrival_offsets(process: ArrivalProcess, count: usize) -> Vec<u64> {
+        let mut tx_vec: Vec<TransactionParams> = (0..count)
+            .map(|_| TransactionParams {
+                typ: TransactionType::NewOrder,
+                keying_time_ms: 18_000,
+                think_time_ms: 12_000,
+                is_rbk: false,
+                input: None,
+                start_offset_ms: None,
+            })
+            .collect();
+        let arrival = ArrivalSchedule {
+            process,
+            rate_tpm: 6.,
+        };
+        schedule_arrivals(&mut tx_vec, &arrival, &mut term_rng(42, 1, 1));
+        tx_vec
+            .iter()
+            .map(|tx| tx.start_offset_ms.unwrap())
+            .collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "pass --non-compliant")]
+    fn open_loop_runs_require_non_compliant() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut params = gen_params(out_dir.path(), 42);
+        params.arrival = Some(ArrivalSchedule {
+            process: ArrivalProcess::Poisson,
+            rate_tpm: 4.,
+        });
+        gen_cfg(params);
+    }
+
+    #[test]
+    fn open_loop_runs_record_the_violation() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut params = gen_params(out_dir.path(), 42);
+        params.arrival = Some(ArrivalSchedule {
+            process: ArrivalProcess::Fixed,
+            rate_tpm: 4.,
+        });
+        params.non_compliant = true;
+        gen_cfg(params);
+
+        let cfg = read_cfg(&out_dir.path().join("W1_T1.cfg")).unwrap();
+        assert_eq!(cfg.header.non_compliance.len(), 1);
+        assert!(cfg.header.non_compliance[0].contains("Open-loop Fixed arrivals at 4 tpm"));
+        let manifest = RunManifest::from_file(&out_dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        assert_eq!(manifest.non_compliance, cfg.header.non_compliance);
+    }
+
+    #[test]
+    fn idle_warehouses_are_kept_without_terminals() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut params = gen_params(out_dir.path(), 42);
+        params.terminal_map = Some(parse_terminal_map("2=0").unwrap());
+        params.non_compliant = true;
+        gen_cfg(params);
+
+        assert!(out_dir.path().join("W1_T10.cfg").exists());
+        assert!(!out_dir.path().join("W2_T1.cfg").exists());
+        let manifest = RunManifest::from_file(&out_dir.path().join(MANIFEST_FILE_NAME)).unwrap();
+        assert_eq!(manifest.terminal_count, 2 * DISTRICTS_PER_WAREHOUSE as u64);
+        assert_eq!(manifest.terminals_per_warehouse.get(&2), Some(&0));
+        assert_eq!(manifest.non_compliance.len(), 1);
+        assert!(
+            manifest.non_compliance[0].starts_with("Terminal count 0 must be 10"),
+            "{:?}",
+            manifest.non_compliance
+        );
+    }
+
+    #[test]
+    fn time_scales_apply_to_the_generated_times() {
+        let out_dir = tempfile::tempdir().unwrap();
+        let mut params = gen_params(out_dir.path(), 42);
+        let time_scale = TimeScale {
+            think_time: 0.5,
+            keying_time: 0.,
+        };
+        params.time_scale = time_scale;
+        params.non_compliant = true;
+        gen_cfg(params);
+
+        let cfg = read_cfg(&out_dir.path().join("W2_T3.cfg")).unwrap();
+        assert_eq!(cfg.header.time_scale, Some(time_scale));
+        assert_eq!(
+            cfg.header.non_compliance,
+            vec![
+                "Think times are scaled by 0.5, the TPC-C minimums no longer hold",
+                "Keying times are scaled by 0, the TPC-C minimums no longer hold",
+            ]
+        );
+        let profile = WorkloadProfile::default();
+        cfg.think_time_distribution.iter().for_each(|dstr| {
+            assert_eq!(dstr.mean_ms, profile.get(&dstr.typ).think_time_mean_ms / 2);
+            assert_eq!(dstr.max_ms, dstr.mean_ms * THINK_TIME_MAX_FACTOR);
+        });
+        assert!(cfg.transactions_to_run.iter().all(|tx| {
+            let mean_ms = profile.get(&tx.typ).think_time_mean_ms / 2;
+            tx.keying_time_ms == 0 && tx.think_time_ms <= mean_ms * THINK_TIME_MAX_FACTOR
+        }));
+        // Unscaled runs leave the scale out
+        let out_dir = tempfile::tempdir().unwrap();
+        gen_cfg(gen_params(out_dir.path(), 42));
+        let cfg = read_cfg(&out_dir.path().join("W2_T3.cfg")).unwrap();
+        assert_eq!(cfg.header.time_scale, None);
+        assert!(cfg
+            .transactions_to_run
+            .iter()
+            .all(|tx| tx.keying_time_ms == profile.get(&tx.typ).keying_time_ms));
+    }
+
+    #[test]
+    fn fixed_arrivals_keep_the_interval() {
+        let offsets = arrival_offsets(ArrivalProcess::Fixed, 100);
+        assert!(offsets
+            .iter()
+            .enumerate()
+            .all(|(i, offset)| *offset == i as u64 * 10_000));
+    }
+
+    #[test]
+    fn poisson_arrivals_hit_the_target_rate() {
+        let count = 100_000;
+        let offsets = arrival_offsets(ArrivalProcess::Poisson, count);
+        assert_eq!(offsets[0], 0);
+        assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+        // Within 2% of 6 tpm, more than 6 standard deviations of the mean interval over 100000 draws
+        let rate_tpm = (count - 1) as f64 * 60_000. / *offsets.last().unwrap() as f64;
+        assert!((rate_tpm - 6.).abs() < 6. * 0.02, "{}", rate_tpm);
+        // Exponential intervals: about 1/e of them are longer than the mean
+        let longer = offsets
+            .windows(2)
+            .filter(|pair| pair[1] - pair[0] > 10_000)
+            .count() as f64
+            / (count - 1) as f64;
+        assert!((longer - (-1f64).exp()).abs() < 0.01, "{}", longer);
+    }
+
+    #[test]
+    fn seeds_above_i64_max_are_rejected() {
+        assert_eq!(parse_seed("42").unwrap(), 42);
+        assert_eq!(parse_seed(&MAX_SEED.to_string()).unwrap(), MAX_SEED);
+        let err = parse_seed(&u64::MAX.to_string()).unwrap_err();
+        assert!(err.err_msg.contains("too large"), "{}", err.err_msg);
+        assert!(parse_seed("-1").is_err());
+        assert!(parse_seed("seed").is_err());
+    }
 }
@@ -0,0 +1,365 @@
+use std::cmp::{max, min};
+use std::path::Path;
+
+use glob::glob;
+use rayon::prelude::*;
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::cfg::*;
+use crate::profile::{mix_violation, WorkloadProfile};
+
+arg_enum! {
+    /// Output of the config summary. Either 'Table' or 'Json'
+    #[derive(StructOpt, Debug, Clone, Copy)]
+    pub enum InspectOutput {
+        Table,
+        Json
+    }
+}
+
+/// Summary of the transactions of a single terminal or of all the terminals inspected
+#[derive(Serialize, Debug)]
+pub struct CfgSummary {
+    /// `W{w}_T{t}` of a terminal or `all` for the aggregate
+    pub terminal: String,
+    pub transaction_count: u32,
+    pub mix: Vec<TransactionShare>,
+    pub rollback_count: u32,
+    pub think_time_mean_ms: f64,
+    pub think_time_min_ms: u32,
+    pub think_time_max_ms: u32,
+    pub keying_time_mean_ms: f64,
+    /// Mean keying, think and expected response time of a transaction
+    pub expected_cycle_time_ms: f64,
+    /// Expected time to run through all the transactions, the longest terminal's one for the aggregate
+    pub expected_run_time_ms: u64,
+    /// NewOrder transactions per minute over the expected run time, summed up for the aggregate
+    pub expected_tpmc: f64,
+    /// Decks breaking the TPC-C minimum mix
+    pub deck_violations: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TransactionShare {
+    #[serde(rename = "type")]
+    pub typ: TransactionType,
+    pub count: u32,
+    pub percent: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InspectReport {
+    pub terminals: Vec<CfgSummary>,
+    pub aggregate: CfgSummary,
+}
+
+/// Running totals the summaries are built from
+#[derive(Default)]
+struct Totals {
+    counts: [u32; 5],
+    rollback_count: u32,
+    think_time_sum_ms: u64,
+    think_time_min_ms: Option<u32>,
+    think_time_max_ms: u32,
+    keying_time_sum_ms: u64,
+    expected_time_sum_ms: u64,
+}
+
+impl Totals {
+    fn add(&mut self, tx: &TransactionParams, profile: &WorkloadProfile) {
+        self.counts[type_idx(&tx.typ)] += 1;
+        if tx.is_rbk {
+            self.rollback_count += 1;
+        }
+        self.think_time_sum_ms += tx.think_time_ms as u64;
+        self.think_time_min_ms = Some(
+            self.think_time_min_ms
+                .map_or(tx.think_time_ms, |m| min(m, tx.think_time_ms)),
+        );
+        self.think_time_max_ms = max(self.think_time_max_ms, tx.think_time_ms);
+        self.keying_time_sum_ms += tx.keying_time_ms as u64;
+        self.expected_time_sum_ms +=
+            (tx.keying_time_ms + tx.think_time_ms + profile.get(&tx.typ).expected_rt_ms) as u64;
+    }
+
+    fn merge(&mut self, other: &Totals) {
+        (0..self.counts.len()).for_each(|i| self.counts[i] += other.counts[i]);
+        self.rollback_count += other.rollback_count;
+        self.think_time_sum_ms += other.think_time_sum_ms;
+        self.think_time_min_ms = match (self.think_time_min_ms, other.think_time_min_ms) {
+            (Some(a), Some(b)) => Some(min(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.think_time_max_ms = max(self.think_time_max_ms, other.think_time_max_ms);
+        self.keying_time_sum_ms += other.keying_time_sum_ms;
+        self.expected_time_sum_ms += other.expected_time_sum_ms;
+    }
+
+    fn transaction_count(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    fn summary(
+        &self,
+        terminal: String,
+        expected_run_time_ms: u64,
+        expected_tpmc: f64,
+        deck_violations: Vec<String>,
+    ) -> CfgSummary {
+        let count = self.transaction_count();
+        let mean = |sum: u64| match count {
+            0 => 0.,
+            _ => sum as f64 / count as f64,
+        };
+        CfgSummary {
+            terminal,
+            transaction_count: count,
+            mix: TransactionType::iter()
+                .map(|typ| {
+                    let type_count = self.counts[type_idx(typ)];
+                    TransactionShare {
+                        typ: typ.clone(),
+                        count: type_count,
+                        percent: mean(type_count as u64 * 100),
+                    }
+                })
+                .collect(),
+            rollback_count: self.rollback_count,
+            think_time_mean_ms: mean(self.think_time_sum_ms),
+            think_time_min_ms: self.think_time_min_ms.unwrap_or(0),
+            think_time_max_ms: self.think_time_max_ms,
+            keying_time_mean_ms: mean(self.keying_time_sum_ms),
+            expected_cycle_time_ms: mean(self.expected_time_sum_ms),
+            expected_run_time_ms,
+            expected_tpmc,
+            deck_violations,
+        }
+    }
+}
+
+fn type_idx(typ: &TransactionType) -> usize {
+    TransactionType::iter().position(|t| t == typ).unwrap()
+}
+
+/// Summarize the config files matching the patterns and the terminals of the bundle, if any.
+/// The expected response times and the deck size are taken from the profile
+pub fn inspect_cfg(
+    cfg_files: &[String],
+    bundle_index: Option<String>,
+    profile: &WorkloadProfile,
+    output: InspectOutput,
+) {
+    let mut cfgs: Vec<TermControlCfg> = cfg_files
+        .iter()
+        .flat_map(|pattern| glob(pattern).expect("Failed to read glob pattern"))
+        .filter_map(|entry| match entry {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                None
+            }
+        })
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|path| read_cfg(path).unwrap_or_else(|e| panic!("{}", e.err_msg)))
+        .collect();
+    if let Some(index_path) = bundle_index {
+        let index_path = Path::new(&index_path);
+        let index = BundleIndex::from_file(index_path).unwrap_or_else(|e| panic!("{}", e.err_msg));
+        let bundle_dir = index_path.parent().unwrap_or_else(|| Path::new(""));
+        cfgs.extend(
+            index
+                .terminals
+                .par_iter()
+                .map(|e| {
+                    index
+                        .read_cfg(bundle_dir, e.home_warehouse_id, e.this_terminal_id)
+                        .unwrap_or_else(|e| panic!("{}", e.err_msg))
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+    if cfgs.is_empty() {
+        panic!("No terminal configs found");
+    }
+    cfgs.sort_by_key(|cfgz| (cfgz.home_warehouse_id, cfgz.this_terminal_id));
+
+    let report = summarize(&cfgs, profile);
+    match output {
+        InspectOutput::Json => println!("{}", report_json(&report)),
+        InspectOutput::Table => print_table(&report),
+    }
+}
+
+/// Summary of every terminal config and of all of them together
+fn summarize(cfgs: &[TermControlCfg], profile: &WorkloadProfile) -> InspectReport {
+    let mut all = Totals::default();
+    let mut all_run_time_ms = 0;
+    let mut all_tpmc = 0.;
+    let mut all_violations = Vec::new();
+    let terminals: Vec<CfgSummary> = cfgs
+        .iter()
+        .map(|cfgz| {
+            let mut totals = Totals::default();
+            cfgz.transactions_to_run
+                .iter()
+                .for_each(|tx| totals.add(tx, profile));
+            let name = format!("W{}_T{}", cfgz.home_warehouse_id, cfgz.this_terminal_id);
+            let violations = deck_violations(&name, &cfgz.transactions_to_run, profile.deck_size);
+            let new_order_count = totals.counts[type_idx(&TransactionType::NewOrder)];
+            let tpmc = match totals.expected_time_sum_ms {
+                0 => 0.,
+                ms => new_order_count as f64 * 60_000. / ms as f64,
+            };
+            all.merge(&totals);
+            all_run_time_ms = max(all_run_time_ms, totals.expected_time_sum_ms);
+            all_tpmc += tpmc;
+            all_violations.extend(violations.iter().cloned());
+            totals.summary(name, totals.expected_time_sum_ms, tpmc, violations)
+        })
+        .collect();
+    InspectReport {
+        terminals,
+        aggregate: all.summary("all".to_string(), all_run_time_ms, all_tpmc, all_violations),
+    }
+}
+
+fn report_json(report: &InspectReport) -> String {
+    serde_json::to_string_pretty(report).expect("Unsupported summary format")
+}
+
+/// Check every whole deck of the transaction list against the TPC-C minimum mix
+fn deck_violations(name: &str, txs: &[TransactionParams], deck_size: u32) -> Vec<String> {
+    txs.chunks(deck_size as usize)
+        .enumerate()
+        .filter(|(_, deck)| deck.len() == deck_size as usize)
+        .flat_map(|(deck_idx, deck)| {
+            TransactionType::iter()
+                .filter_map(|typ| {
+                    let card_count = deck.iter().filter(|tx| tx.typ == *typ).count() as u32;
+                    mix_violation(typ, card_count, deck_size)
+                })
+                .map(|violation| format!("{} deck {}: {}", name, deck_idx + 1, violation))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn print_table(report: &InspectReport) {
+    println!(
+        "{:<12} {:>7} {:>6} {:>6} {:>6} {:>6} {:>6} {:>5} {:>10} {:>8} {:>8} {:>10} {:>10} {:>12} {:>8}",
+        "Terminal",
+        "Txs",
+        "NO%",
+        "P%",
+        "OS%",
+        "D%",
+        "SL%",
+        "Rbk",
+        "Think avg",
+        "min",
+        "max",
+        "Keying avg",
+        "Cycle avg",
+        "Run time",
+        "tpmC"
+    );
+    report
+        .terminals
+        .iter()
+        .chain(std::iter::once(&report.aggregate))
+        .for_each(|s| {
+            let mix: Vec<String> = s
+                .mix
+                .iter()
+                .map(|m| format!("{:>6.2}", m.percent))
+                .collect();
+            println!(
+                "{:<12} {:>7} {} {:>5} {:>10.0} {:>8} {:>8} {:>10.0} {:>10.0} {:>12} {:>8.3}",
+                s.terminal,
+                s.transaction_count,
+                mix.join(" "),
+                s.rollback_count,
+                s.think_time_mean_ms,
+                s.think_time_min_ms,
+                s.think_time_max_ms,
+                s.keying_time_mean_ms,
+                s.expected_cycle_time_ms,
+                humantime::format_duration(std::time::Duration::from_secs(
+                    s.expected_run_time_ms / 1000
+                ))
+                .to_string(),
+                s.expected_tpmc
+            );
+        });
+    if report.aggregate.deck_violations.is_empty() {
+        println!("All decks meet the TPC-C minimum mix");
+    } else {
+        println!("Decks breaking the TPC-C minimum mix:");
+        report
+            .aggregate
+            .deck_violations
+            .iter()
+            .for_each(|v| println!("  {}", v));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tx_breakdown;
+
+    /// Config of a terminal running a single deck of the profile, the cards in the type order
+    fn deck_cfg(w: u32, t: u32, profile: &WorkloadProfile) -> TermControlCfg {
+        let tx_bkdwn = tx_breakdown(profile.deck_size, profile);
+        TermControlCfg {
+            header: CfgHeader::default(),
+            home_warehouse_id: w,
+            this_terminal_id: t,
+            district_id: Some(t),
+            start_delay_ms: 0,
+            seed: None,
+            think_time_distribution: Vec::new(),
+            transactions_to_run: TransactionType::iter()
+                .flat_map(|typ| {
+                    let dflts = &tx_bkdwn[typ];
+                    (0..dflts.tx_count).map(move |_| TransactionParams {
+                        typ: typ.clone(),
+                        keying_time_ms: dflts.keying_time_ms,
+                        think_time_ms: dflts.think_time_mean_ms,
+                        is_rbk: false,
+                        input: None,
+                        start_offset_ms: None,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn json_output_parses() {
+        let profile = WorkloadProfile::default();
+        let cfgs = vec![deck_cfg(1, 1, &profile), deck_cfg(1, 2, &profile)];
+        let json: serde_json::Value =
+            serde_json::from_str(&report_json(&summarize(&cfgs, &profile))).unwrap();
+
+        let terminals = json["terminals"].as_array().unwrap();
+        assert_eq!(terminals.len(), 2);
+        assert_eq!(terminals[0]["terminal"], "W1_T1");
+        assert_eq!(terminals[1]["terminal"], "W1_T2");
+        assert_eq!(json["aggregate"]["terminal"], "all");
+        assert_eq!(
+            json["aggregate"]["transaction_count"],
+            2 * profile.deck_size
+        );
+        let mix = json["aggregate"]["mix"].as_array().unwrap();
+        assert_eq!(mix.len(), TransactionType::iter().len());
+        assert_eq!(mix[0]["type"], "NewOrder");
+        assert!(json["aggregate"]["deck_violations"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        assert!(json["aggregate"]["expected_tpmc"].as_f64().unwrap() > 0.);
+    }
+}
@@ -0,0 +1,381 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use structopt::StructOpt;
+
+use crate::naming::{parse_command_template, render, NameVars};
+use crate::util::Error;
+
+/// Directory, relative to the configs, the emulators are expected to write their logs to
+pub const LAUNCH_LOG_DIR: &str = "logs";
+pub const LAUNCH_SCRIPT_NAME: &str = "launch.sh";
+pub const SYSTEMD_UNIT_DIR: &str = "systemd";
+pub const COMPOSE_FILE_NAME: &str = "docker-compose.yaml";
+
+/// Directory the config directory is mounted at in the containers
+const COMPOSE_WORK_DIR: &str = "/cfg";
+
+arg_enum! {
+    /// Launch artifact. 'Shell' writes a POSIX shell script, 'Systemd' a unit file per service,
+    /// 'Compose' a docker-compose file
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum LaunchKind {
+        Shell,
+        Systemd,
+        Compose
+    }
+}
+
+arg_enum! {
+    /// What a launched service runs. Either an emulator per 'Terminal' or one per driver 'Host'
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum LaunchUnit {
+        Terminal,
+        Host
+    }
+}
+
+// Launch artifacts written next to the terminal configs of every driver host.
+// Not a doc comment: it would replace the description of the subcommands flattening the struct
+#[derive(StructOpt, Serialize, Deserialize, Debug, Clone)]
+pub struct LaunchParams {
+    /// Launch artifacts to write next to the configs, comma separated. Either 'Shell', 'Systemd' or 'Compose'
+    #[structopt(long, use_delimiter = true, requires = "launch-command")]
+    pub launch: Vec<LaunchKind>,
+
+    /// Command starting an emulator, run from the config directory.
+    /// Accepts the `{cfg}`, `{log}`, `{w}`, `{t}`, `{host}`, `{ts}` and `{label}` placeholders,
+    /// e.g. `emulator --cfg {cfg} --log {log}`
+    #[structopt(long, parse(try_from_str = parse_command_template))]
+    pub launch_command: Option<String>,
+
+    /// Service granularity. Either 'Terminal' or 'Host', the latter requires `--driver-hosts`
+    #[structopt(long, default_value = "Terminal")]
+    pub launch_unit: LaunchUnit,
+
+    /// Container image of the emulator, required by the 'Compose' artifact
+    #[structopt(long)]
+    pub launch_image: Option<String>,
+
+    /// Directory the systemd units run in on the driver hosts. Accepts the `{host}`, `{ts}` and `{label}` placeholders,
+    /// defaults to the absolute path of the generated config directory
+    #[structopt(long, parse(try_from_str = parse_command_template))]
+    pub launch_work_dir: Option<String>,
+}
+
+impl Default for LaunchParams {
+    fn default() -> Self {
+        LaunchParams {
+            launch: Vec::new(),
+            launch_command: None,
+            launch_unit: LaunchUnit::Terminal,
+            launch_image: None,
+            launch_work_dir: None,
+        }
+    }
+}
+
+/// An emulator process: a single terminal or every terminal of a driver host
+struct LaunchService {
+    name: String,
+    description: String,
+    command: String,
+}
+
+impl LaunchParams {
+    /// Check the launch parameters fit together before anything is generated
+    pub fn validate(&self, has_driver_hosts: bool) -> Result<(), Error> {
+        if self.launch.is_empty() {
+            return Ok(());
+        }
+        let command = self.launch_command.as_deref().ok_or_else(|| Error {
+            err_msg: "Launch artifacts require --launch-command".to_string(),
+        })?;
+        if self.launch.contains(&LaunchKind::Compose) && self.launch_image.is_none() {
+            return Err(Error {
+                err_msg: "Compose launch artifact requires --launch-image".to_string(),
+            });
+        }
+        let work_dir = self.launch_work_dir.as_deref().unwrap_or("");
+        if !has_driver_hosts && (command.contains("{host}") || work_dir.contains("{host}")) {
+            return Err(Error {
+                err_msg: "Placeholder {host} of the launch templates requires --driver-hosts"
+                    .to_string(),
+            });
+        }
+        if self.launch_unit == LaunchUnit::Host {
+            if !has_driver_hosts {
+                return Err(Error {
+                    err_msg: "Launch unit Host requires --driver-hosts".to_string(),
+                });
+            }
+            if command.contains("{w}") || command.contains("{t}") {
+                return Err(Error {
+                    err_msg: format!(
+                        "Launch command '{}' of the Host unit cannot use the {{w}} and {{t}} placeholders",
+                        command
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the launch artifacts of a driver host, or of the whole run without driver hosts, into its config directory.
+/// `cfg_names` are the config file names of the terminals, `bundle_index` the index name in bundle mode.
+/// Returns the names of the files written relative to the directory
+pub fn write_launch_files(
+    dir: &Path,
+    host: Option<&str>,
+    params: &LaunchParams,
+    name_vars: &NameVars,
+    terms: &[(u32, u32)],
+    cfg_names: &[String],
+    bundle_index: Option<&str>,
+) -> Vec<String> {
+    if params.launch.is_empty() {
+        return Vec::new();
+    }
+    let command = params.launch_command.as_deref().unwrap();
+    let host_vars = NameVars { host, ..*name_vars };
+    // The commands are run by a shell, the values are substituted as single words
+    let quoted_label = name_vars.label.map(shell_quote);
+    let quoted_host = host.map(shell_quote);
+    let command_vars = NameVars {
+        label: quoted_label.as_deref(),
+        host: quoted_host.as_deref(),
+        ..*name_vars
+    };
+    let services: Vec<LaunchService> = match (params.launch_unit, host) {
+        (LaunchUnit::Host, Some(host)) => vec![LaunchService {
+            name: host.to_string(),
+            description: format!("TPC-C terminal emulator of the driver host {}", host),
+            command: render(
+                command,
+                &NameVars {
+                    cfg: Some(&shell_quote(bundle_index.unwrap_or("."))),
+                    log: Some(LAUNCH_LOG_DIR),
+                    ..command_vars
+                },
+            )
+            .unwrap_or_else(|e| panic!("{}", e.err_msg)),
+        }],
+        _ => terms
+            .iter()
+            .enumerate()
+            .map(|(idx, (w, t))| {
+                let log = format!("{}/W{}_T{}.csv", LAUNCH_LOG_DIR, w, t);
+                LaunchService {
+                    name: format!("w{}-t{}", w, t),
+                    description: format!("TPC-C terminal emulator W{} T{}", w, t),
+                    command: render(
+                        command,
+                        &NameVars {
+                            w: Some(*w),
+                            t: Some(*t),
+                            cfg: Some(&shell_quote(
+                                bundle_index.unwrap_or_else(|| &cfg_names[idx]),
+                            )),
+                            log: Some(&shell_quote(&log)),
+                            ..command_vars
+                        },
+                    )
+                    .unwrap_or_else(|e| panic!("{}", e.err_msg)),
+                }
+            })
+            .collect(),
+    };
+
+    params
+        .launch
+        .iter()
+        .flat_map(|kind| match kind {
+            LaunchKind::Shell => vec![write_shell_script(dir, &services)],
+            LaunchKind::Systemd => {
+                let work_dir = match &params.launch_work_dir {
+                    Some(template) => {
+                        render(template, &host_vars).unwrap_or_else(|e| panic!("{}", e.err_msg))
+                    }
+                    None => fs::canonicalize(dir)
+                        .unwrap_or_else(|e| {
+                            panic!("Error resolving config directory {:?}: {}", dir, e)
+                        })
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                write_systemd_units(dir, &work_dir, &services)
+            }
+            LaunchKind::Compose => vec![write_compose_file(
+                dir,
+                params.launch_image.as_deref().unwrap(),
+                &services,
+            )],
+        })
+        .collect()
+}
+
+/// Value as a single shell word: kept as is if it has no special characters, single-quoted otherwise
+pub fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,/:=@%+".contains(c));
+    match plain {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+/// Script starting every service in the background and waiting for all of them
+fn write_shell_script(dir: &Path, services: &[LaunchService]) -> String {
+    let mut script = format!(
+        "#!/bin/sh\n# Starts {} terminal emulator service(s), the logs are written to {}/\ncd \"$(dirname \"$0\")\" || exit 1\nmkdir -p {}\n",
+        services.len(),
+        LAUNCH_LOG_DIR,
+        LAUNCH_LOG_DIR
+    );
+    services.iter().for_each(|service| {
+        script += &format!("# {}\n{} &\n", service.description, service.command);
+    });
+    script += "wait\n";
+    let path = dir.join(LAUNCH_SCRIPT_NAME);
+    fs::write(&path, script)
+        .unwrap_or_else(|e| panic!("Error writing launch script {:?}: {}", &path, e));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .unwrap_or_else(|e| panic!("Error making launch script {:?} executable: {}", &path, e));
+    }
+    LAUNCH_SCRIPT_NAME.to_string()
+}
+
+/// A unit file per service, named `tpcc-emulator-{service}.service`
+fn write_systemd_units(dir: &Path, work_dir: &str, services: &[LaunchService]) -> Vec<String> {
+    let unit_dir = dir.join(SYSTEMD_UNIT_DIR);
+    fs::create_dir_all(&unit_dir).unwrap_or_else(|e| {
+        panic!(
+            "Error creating systemd unit directory {:?}: {}",
+            &unit_dir, e
+        )
+    });
+    services
+        .iter()
+        .map(|service| {
+            let unit = format!(
+                "[Unit]\nDescription={}\nAfter=network-online.target\n\n\
+                 [Service]\nType=simple\nWorkingDirectory={}\nExecStartPre=/bin/mkdir -p {}\nExecStart={}\n\n\
+                 [Install]\nWantedBy=multi-user.target\n",
+                systemd_escape(&service.description),
+                systemd_escape(work_dir),
+                LAUNCH_LOG_DIR,
+                systemd_escape(&service.command)
+            );
+            let unit_name = format!("tpcc-emulator-{}.service", service.name);
+            let path = unit_dir.join(&unit_name);
+            fs::write(&path, unit).unwrap_or_else(|e| panic!("Error writing systemd unit {:?}: {}", &path, e));
+            format!("{}/{}", SYSTEMD_UNIT_DIR, unit_name)
+        })
+        .collect()
+}
+
+/// Value of a unit file setting with `%` escaped, systemd would expand it as a specifier
+fn systemd_escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+/// Compose file with a service per emulator, the config directory is mounted as the working directory
+fn write_compose_file(dir: &Path, image: &str, services: &[LaunchService]) -> String {
+    let mut compose_services = Mapping::new();
+    services.iter().for_each(|service| {
+        let mut compose_service = Mapping::new();
+        compose_service.insert("image".into(), image.into());
+        compose_service.insert("working_dir".into(), COMPOSE_WORK_DIR.into());
+        compose_service.insert(
+            "volumes".into(),
+            Value::Sequence(vec![format!(".:{}", COMPOSE_WORK_DIR).into()]),
+        );
+        compose_service.insert(
+            "command".into(),
+            Value::Sequence(vec![
+                "sh".into(),
+                "-c".into(),
+                format!("mkdir -p {} && {}", LAUNCH_LOG_DIR, service.command).into(),
+            ]),
+        );
+        compose_services.insert(service.name.clone().into(), Value::Mapping(compose_service));
+    });
+    let mut compose = Mapping::new();
+    compose.insert("services".into(), Value::Mapping(compose_services));
+    let path = dir.join(COMPOSE_FILE_NAME);
+    fs::write(
+        &path,
+        serde_yaml::to_string(&compose).expect("Unsupported compose file format"),
+    )
+    .unwrap_or_else(|e| panic!("Error writing compose file {:?}: {}", &path, e));
+    COMPOSE_FILE_NAME.to_string()
+}
+
+/// Pattern of the log files the launched emulators write, as `test-report` expects it
+pub fn log_files_glob(cfg_file_dir: &Path, has_driver_hosts: bool) -> String {
+    let log_dir = match has_driver_hosts {
+        true => cfg_file_dir.join("*").join(LAUNCH_LOG_DIR),
+        false => cfg_file_dir.join(LAUNCH_LOG_DIR),
+    };
+    log_dir.join("*.csv").to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_shell_quoted() {
+        assert_eq!(shell_quote("logs/W1_T1.csv"), "logs/W1_T1.csv");
+        assert_eq!(shell_quote("run 1"), "'run 1'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn systemd_units_escape_specifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = LaunchParams {
+            launch: vec![LaunchKind::Systemd],
+            launch_command: Some("emulator --cfg {cfg} --log {log} --tag $(date +%F)".to_string()),
+            launch_work_dir: Some("/srv/tpcc/100%/{ts}".to_string()),
+            ..LaunchParams::default()
+        };
+        let names = write_launch_files(
+            dir.path(),
+            None,
+            &params,
+            &NameVars {
+                ts: "20200101_000000",
+                ..Default::default()
+            },
+            &[(1, 1)],
+            &["W1_T1 50%.cfg".to_string()],
+            None,
+        );
+        assert_eq!(names, vec!["systemd/tpcc-emulator-w1-t1.service"]);
+        let unit = fs::read_to_string(dir.path().join(&names[0])).unwrap();
+        assert!(
+            unit.contains("WorkingDirectory=/srv/tpcc/100%%/20200101_000000\n"),
+            "{}",
+            unit
+        );
+        assert!(
+            unit.contains(
+                "ExecStart=emulator --cfg 'W1_T1 50%%.cfg' --log logs/W1_T1.csv --tag $(date +%%F)\n"
+            ),
+            "{}",
+            unit
+        );
+        assert!(!unit.replace("%%", "").contains('%'), "{}", unit);
+    }
+}
@@ -1,4 +1,20 @@
+#[macro_use]
+extern crate clap;
+
 pub mod cfg;
 pub mod generator;
+pub mod inspect;
+pub mod launch;
+pub mod manifest;
+pub mod migrate;
+pub mod naming;
+pub mod plan;
+pub mod populate;
+pub mod profile;
+pub mod rampup;
+pub mod schema;
+pub mod sharding;
 pub mod terminal;
+pub mod terminal_map;
+pub mod tpcc;
 pub mod util;
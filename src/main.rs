@@ -1,3 +1,5 @@
+use std::io::{self, Write};
+use std::path::Path;
 use std::time::Duration;
 
 use humantime::parse_duration;
@@ -6,38 +8,279 @@ use structopt::StructOpt;
 use glob::glob;
 
 use self::util::parse_nums;
-use std::error;
-use std::error::Error;
-use std::str::FromStr;
-use std::string::ParseError;
 
 #[macro_use]
 extern crate clap;
 
 mod cfg;
 mod generator;
+mod inspect;
+mod launch;
+mod manifest;
+mod migrate;
+mod naming;
+mod plan;
+mod populate;
+mod profile;
+mod rampup;
 mod reporting;
+mod schema;
+mod sharding;
 mod terminal;
+mod terminal_map;
+mod tpcc;
 mod util;
 
+use self::cfg::{ArrivalProcess, ArrivalSchedule, BundleFormat, CfgFormat};
+use self::generator::parse_seed;
+use self::inspect::InspectOutput;
+use self::launch::LaunchParams;
+use self::manifest::{RunManifest, MANIFEST_FILE_NAME};
+use self::naming::{parse_dir_template, OutputNaming};
+use self::plan::PlanTarget;
+use self::profile::{TimeScale, WorkloadProfile};
+use self::rampup::{RampUpMode, RampUpPlan};
 use self::reporting::ReportMode;
+use self::schema::SqlDialect;
+use self::sharding::{parse_driver_hosts, DriverHosts};
+use self::terminal_map::{parse_terminal_map, TerminalMap};
 
+// Parsed once at the start, the size of the variants does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(StructOpt, Debug)]
 pub enum RunMode {
     /// Generate terminal configuration files
     Generate {
-        /// List of warehouse IDs. Can be a single value, a comma separated list or a range (both ends are included)
+        /// List of warehouse IDs. A comma separated mix of values and ranges (both ends are included), e.g. `1..5,8,10..12`.
+        /// Ranges take a step, `1..100:10`, `!7` excludes a value or a range and `@ids.txt` reads the list from a file
         /// Example: `-w 1..5` will generate configuration for five warehouses starting from 1
+        #[structopt(
+            short = "w",
+            long,
+            parse(try_from_str = parse_nums),
+            required_unless = "terminal-map"
+        )]
+        warehouse_id_list: Option<Box<Vec<u32>>>,
+
+        /// Generate configuration for this many terminals per warehouse
+        #[structopt(short = "t", long, required_unless = "terminal-map")]
+        terminal_count: Option<u32>,
+
+        /// Terminal count per warehouse, e.g. `1..5=10;7=20;8=0`, or `@file` with an entry per line.
+        /// Adds the warehouses to the list and overrides the terminal count for them
+        #[structopt(long, parse(try_from_str = parse_terminal_map))]
+        terminal_map: Option<TerminalMap>,
+
+        /// Number of transactions per terminal, rounded up to whole 23-card decks
+        #[structopt(
+            short = "x",
+            long,
+            required_unless = "duration",
+            conflicts_with = "duration"
+        )]
+        transaction_count: Option<u32>,
+
+        /// Run duration the configs must cover instead of the transaction count, ramp-up included.
+        /// Accepts values in a human readable format, e.g. `2h 15m`
+        #[structopt(short = "d", long, parse(try_from_str = parse_duration))]
+        duration: Option<Duration>,
+
+        /// Extra share of the duration in percent generated on top of it
+        #[structopt(long, default_value = "10")]
+        margin_percent: u32,
+
+        /// Seed for reproducible generation. A random one is drawn and written into the configs if omitted
+        #[structopt(short = "s", long, parse(try_from_str = parse_seed))]
+        seed: Option<u64>,
+
+        /// C constant of the C_LAST NURand the database was populated with. Drawn at random if omitted
+        #[structopt(long)]
+        c_last_load: Option<u32>,
+
+        /// Run-time C constant of the C_LAST NURand. Must differ from `--c-last-load` as TPC-C Clause 2.1.6.1 requires.
+        /// Drawn at random if omitted
+        #[structopt(long)]
+        c_last: Option<u32>,
+
+        /// Run-time C constant of the C_ID NURand. Drawn at random if omitted
+        #[structopt(long)]
+        c_id: Option<u32>,
+
+        /// Run-time C constant of the OL_I_ID NURand. Drawn at random if omitted
+        #[structopt(long)]
+        ol_i_id: Option<u32>,
+
+        /// YAML or TOML workload profile with the mix, keying times, mean think times and rollback rates.
+        /// The built-in TPC-C profile is used if omitted
+        #[structopt(short = "p", long, parse(try_from_str = WorkloadProfile::from_file))]
+        profile: Option<WorkloadProfile>,
+
+        /// Generate even though the parameters break the TPC-C rules
+        #[structopt(long)]
+        non_compliant: bool,
+
+        /// Open-loop arrival process setting an intended start offset on every transaction. Either 'Poisson' or 'Fixed'.
+        /// The transactions follow the keying and think times (closed loop) if omitted. Requires `--non-compliant`
+        #[structopt(long, requires = "arrival-rate")]
+        arrival: Option<ArrivalProcess>,
+
+        /// Target arrival rate of a terminal in transactions per minute
+        #[structopt(long, requires = "arrival")]
+        arrival_rate: Option<f64>,
+
+        /// Scale factor of the profile's mean think times, e.g. `0` for zero-think max throughput runs.
+        /// Any factor other than 1 requires `--non-compliant`
+        #[structopt(long, default_value = "1")]
+        think_time_scale: f64,
+
+        /// Scale factor of the profile's keying times. Any factor other than 1 requires `--non-compliant`
+        #[structopt(long, default_value = "1")]
+        keying_time_scale: f64,
+
+        /// Ramp-up mode spreading the terminal start delays. Either 'None', 'Linear', 'Stepped' or 'Waves'
+        #[structopt(long, default_value = "None")]
+        ramp_up: RampUpMode,
+
+        /// Start delay of the last terminal group.
+        /// Accepts values in a human readable format, e.g. `1m` or `1h 15m`
+        #[structopt(long, parse(try_from_str = parse_duration), default_value = "0s")]
+        ramp_up_duration: Duration,
+
+        /// Number of terminal groups for the 'Stepped' ramp-up mode
+        #[structopt(long, default_value = "5")]
+        ramp_up_steps: u32,
+
+        /// Serialization format of the per-terminal config files. Either 'Yaml' (default), 'Json', 'Toml' or 'MessagePack'
+        #[structopt(short = "f", long, conflicts_with = "bundle")]
+        format: Option<CfgFormat>,
+
+        /// Write every terminal config into a single bundle file with an index of byte offsets
+        /// instead of a file per terminal. Either 'Yaml' (multi-document) or 'JsonLines'
+        #[structopt(long)]
+        bundle: Option<BundleFormat>,
+
+        #[structopt(flatten)]
+        naming: OutputNaming,
+
+        /// Spread the terminals over driver hosts, given either as a host count or as a comma separated list of host names.
+        /// Every host gets a subdirectory of its own, whole warehouses stay on one host where possible
+        #[structopt(long, parse(try_from_str = parse_driver_hosts))]
+        driver_hosts: Option<DriverHosts>,
+
+        #[structopt(flatten)]
+        launch: LaunchParams,
+    },
+    /// Print the config of a single terminal read from a config bundle or a config file of any format
+    ExtractCfg {
+        /// Index file written next to the bundle
+        #[structopt(
+            short = "i",
+            long,
+            required_unless = "cfg-file",
+            conflicts_with = "cfg-file"
+        )]
+        bundle_index: Option<String>,
+
+        /// Home warehouse ID of the terminal in the bundle
+        #[structopt(short = "w", long, required_unless = "cfg-file")]
+        warehouse_id: Option<u32>,
+
+        /// Terminal ID within the warehouse in the bundle
+        #[structopt(short = "t", long, required_unless = "cfg-file")]
+        terminal_id: Option<u32>,
+
+        /// Terminal config file to read instead of a bundle, the format is chosen by the file extension
+        #[structopt(short = "c", long)]
+        cfg_file: Option<String>,
+
+        /// Format to print the config in. Either 'Yaml', 'Json', 'Toml' or 'MessagePack'
+        #[structopt(short = "f", long, default_value = "Yaml")]
+        format: CfgFormat,
+    },
+    /// Summarize the transactions of terminal configs: mix, rollbacks, think, keying and expected cycle times, tpmC
+    InspectCfg {
+        /// Terminal config files of any format. Glob patterns are expanded, e.g. "term-config/*/*.cfg"
+        #[structopt(required_unless = "bundle-index")]
+        cfg_files: Vec<String>,
+
+        /// Index of a config bundle, every terminal of the bundle is inspected
+        #[structopt(short = "i", long)]
+        bundle_index: Option<String>,
+
+        /// Workload profile with the expected response times and the deck size the configs were generated with.
+        /// The built-in TPC-C profile is used if omitted
+        #[structopt(short = "p", long, parse(try_from_str = WorkloadProfile::from_file))]
+        profile: Option<WorkloadProfile>,
+
+        /// Output format. Either 'Table' or 'Json'
+        #[structopt(short = "f", long, default_value = "Table")]
+        output: InspectOutput,
+    },
+    /// Check a terminal config directory against the checksums of its run manifest
+    VerifyCfg {
+        /// Directory holding `manifest.yaml` written by `generate`
+        #[structopt(short = "d", long)]
+        cfg_dir: String,
+    },
+    /// Upgrade the terminal configs and bundles of a directory to the current schema version in place
+    MigrateCfg {
+        /// Directory of the terminal configs, subdirectories included
+        #[structopt(short = "d", long)]
+        cfg_dir: String,
+    },
+    /// Size a run from a target tpmC or a warehouse count and print the matching `generate` command line
+    Plan {
+        /// Target throughput in tpmC
+        #[structopt(
+            long,
+            required_unless = "warehouse-count",
+            conflicts_with = "warehouse-count"
+        )]
+        tpmc: Option<f64>,
+
+        /// Number of warehouses to size the run for instead of the target tpmC
+        #[structopt(long)]
+        warehouse_count: Option<u32>,
+
+        /// First ID of the warehouse range
+        #[structopt(long, default_value = "1")]
+        first_warehouse_id: u32,
+
+        /// Run duration passed on to the `generate` command line. Defaults to a deck cycle of warm-up and
+        /// the 2h minimum measurement interval, rounded up to whole minutes.
+        /// Accepts values in a human readable format, e.g. `2h 15m`
+        #[structopt(short = "d", long, parse(try_from_str = parse_duration))]
+        duration: Option<Duration>,
+
+        /// YAML or TOML workload profile with the keying, mean think and expected response times.
+        /// The built-in TPC-C profile is used if omitted
+        #[structopt(short = "p", long)]
+        profile: Option<String>,
+    },
+    /// Generate the initial database population as CSV files
+    Populate {
+        /// List of warehouse IDs. A comma separated mix of values and ranges (both ends are included), e.g. `1..5,8,10..12`.
+        /// Ranges take a step, `1..100:10`, `!7` excludes a value or a range and `@ids.txt` reads the list from a file
         #[structopt(short = "w", long, parse(try_from_str = parse_nums))]
         warehouse_id_list: Box<Vec<u32>>,
 
-        /// Generate configuration for this many terminals
-        #[structopt(short = "t", long)]
-        terminal_count: u32,
+        /// Seed for reproducible generation. A random one is drawn and written into `population.yaml` if omitted
+        #[structopt(short = "s", long, parse(try_from_str = parse_seed))]
+        seed: Option<u64>,
+
+        /// C constant of the C_LAST NURand used for the customer last names. Drawn at random if omitted
+        #[structopt(long)]
+        c_last_load: Option<u32>,
 
-        /// Number of transactions per terminal
-        #[structopt(short = "x", long)]
-        transaction_count: u32,
+        /// SQL dialects to write the schema and the load scripts for. Either 'Postgres', 'MySql' or 'Sqlite'
+        #[structopt(short = "d", long)]
+        sql_dialect: Vec<SqlDialect>,
+        /// Output directory template. Accepts the `{ts}` and `{label}` placeholders, defaults to `population/{ts}`
+        #[structopt(short = "o", long, parse(try_from_str = parse_dir_template))]
+        output_dir: Option<String>,
+        /// Run label substituted for the `{label}` placeholder of the output directory
+        #[structopt(long)]
+        label: Option<String>,
     },
     /// Build test reports
     TestReport {
@@ -46,8 +289,10 @@ pub enum RunMode {
         log_files_glob: String,
         /// Begin of the measurement (steady) interval starting from the latest `time_started` value throughout the log files provided.
         /// Accepts values in a human readable format, e.g. `1m` or `1h 15m`
+        /// The offset suggested by the run manifest, given or found next to the log files, is used from the earliest
+        /// `time_started` value if omitted
         #[structopt(short = "b", long, parse(try_from_str = parse_duration))]
-        steady_begin_offset: Duration,
+        steady_begin_offset: Option<Duration>,
         /// Length of the measurement (steady) interval.
         /// Accepts values in a human readable format, e.g. `1m` or `1h 15m`
         #[structopt(short = "e", long, parse(try_from_str = parse_duration))]
@@ -55,9 +300,16 @@ pub enum RunMode {
         /// Report building mode
         #[structopt(short = "m", long, default_value = "New")]
         report_mode: ReportMode,
-        /// Report path
-        #[structopt(short = "r", long, required_if("report-mode", "Append"))]
+        /// Report path. Accepts the `{ts}` and `{label}` placeholders, defaults to `test-reports/{ts}`
+        #[structopt(short = "r", long, required_if("report-mode", "Append"), parse(try_from_str = parse_dir_template))]
         report_path: Option<String>,
+        /// Run label substituted for the `{label}` placeholder of the report path
+        #[structopt(long)]
+        label: Option<String>,
+        /// Run manifest written by `generate`, suggests the steady-begin offset matching the ramp-up.
+        /// Looked up next to the log files if omitted
+        #[structopt(long)]
+        run_manifest: Option<String>,
     },
     /// Generate sample log files
     SampleLogFiles {
@@ -67,6 +319,15 @@ pub enum RunMode {
         /// Run this many iterations per terminal
         #[structopt(short, long)]
         iter_count: u32,
+        /// Seed for reproducible generation. A random one is drawn and written into the `seed` file if omitted
+        #[structopt(short = "s", long, parse(try_from_str = parse_seed))]
+        seed: Option<u64>,
+        /// Start time of the sample logs in milliseconds since the epoch. Defaults to the current time
+        #[structopt(long)]
+        start_time_ms: Option<u64>,
+
+        #[structopt(flatten)]
+        naming: OutputNaming,
     },
 }
 
@@ -79,14 +340,154 @@ pub struct Opt {
 
 fn main() {
     let opt = Opt::from_args();
-    println!("{:?}", opt);
     match opt.mode {
         RunMode::Generate {
             warehouse_id_list,
             terminal_count,
+            terminal_map,
             transaction_count,
+            duration,
+            margin_percent,
+            seed,
+            c_last_load,
+            c_last,
+            c_id,
+            ol_i_id,
+            profile,
+            non_compliant,
+            arrival,
+            arrival_rate,
+            think_time_scale,
+            keying_time_scale,
+            ramp_up,
+            ramp_up_duration,
+            ramp_up_steps,
+            format,
+            bundle,
+            naming,
+            driver_hosts,
+            launch,
         } => {
-            generator::gen_cfg(*warehouse_id_list, terminal_count, transaction_count);
+            generator::gen_cfg(generator::GenCfgParams {
+                warehouse_id_list: warehouse_id_list.map(|list| *list).unwrap_or_default(),
+                terminal_count,
+                terminal_map,
+                transaction_count,
+                duration,
+                margin_percent,
+                seed,
+                c_last_load,
+                c_last,
+                c_id,
+                ol_i_id,
+                profile: profile.unwrap_or_default(),
+                arrival: arrival.map(|process| ArrivalSchedule {
+                    process,
+                    rate_tpm: arrival_rate.unwrap(),
+                }),
+                time_scale: TimeScale {
+                    think_time: think_time_scale,
+                    keying_time: keying_time_scale,
+                },
+                non_compliant,
+                ramp_up: RampUpPlan::new(ramp_up, ramp_up_duration, ramp_up_steps),
+                format: format.unwrap_or(CfgFormat::Yaml),
+                bundle,
+                naming,
+                driver_hosts,
+                launch,
+            });
+        }
+        RunMode::ExtractCfg {
+            bundle_index,
+            warehouse_id,
+            terminal_id,
+            cfg_file,
+            format,
+        } => {
+            let cfgz = match (bundle_index, cfg_file) {
+                (Some(bundle_index), _) => cfg::read_bundled_cfg(
+                    Path::new(&bundle_index),
+                    warehouse_id.unwrap(),
+                    terminal_id.unwrap(),
+                ),
+                (None, Some(cfg_file)) => cfg::read_cfg(Path::new(&cfg_file)),
+                (None, None) => unreachable!(),
+            }
+            .unwrap_or_else(|e| panic!("{}", e.err_msg));
+            let doc = format
+                .serialize_cfg(&cfgz)
+                .unwrap_or_else(|e| panic!("Error serializing cfg as {}: {}", format, e));
+            io::stdout()
+                .write_all(&doc)
+                .expect("Error writing cfg to the standard output");
+        }
+        RunMode::InspectCfg {
+            cfg_files,
+            bundle_index,
+            profile,
+            output,
+        } => {
+            inspect::inspect_cfg(
+                &cfg_files,
+                bundle_index,
+                &profile.unwrap_or_default(),
+                output,
+            );
+        }
+        RunMode::VerifyCfg { cfg_dir } => {
+            let cfg_dir = Path::new(&cfg_dir);
+            let report = RunManifest::from_file(&cfg_dir.join(MANIFEST_FILE_NAME))
+                .and_then(|manifest| manifest.verify(cfg_dir))
+                .unwrap_or_else(|e| panic!("{}", e.err_msg));
+            report
+                .missing
+                .iter()
+                .for_each(|f| println!("Missing {}", f));
+            report.extra.iter().for_each(|f| println!("Extra {}", f));
+            report
+                .modified
+                .iter()
+                .for_each(|f| println!("Modified {}", f));
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
+            println!("All files match the manifest");
+        }
+        RunMode::MigrateCfg { cfg_dir } => {
+            migrate::migrate_cfg_dir(Path::new(&cfg_dir));
+        }
+        RunMode::Plan {
+            tpmc,
+            warehouse_count,
+            first_warehouse_id,
+            duration,
+            profile,
+        } => {
+            let target = match (tpmc, warehouse_count) {
+                (Some(tpmc), _) => PlanTarget::Tpmc(tpmc),
+                (None, Some(count)) => PlanTarget::WarehouseCount(count),
+                (None, None) => unreachable!(),
+            };
+            plan::plan_run(target, first_warehouse_id, duration, profile)
+                .unwrap_or_else(|e| panic!("{}", e.err_msg));
+        }
+        RunMode::Populate {
+            warehouse_id_list,
+            seed,
+            c_last_load,
+            sql_dialect,
+            output_dir,
+            label,
+        } => {
+            populate::populate(
+                *warehouse_id_list,
+                seed,
+                c_last_load,
+                sql_dialect,
+                output_dir,
+                label,
+            );
         }
         RunMode::TestReport {
             log_files_glob,
@@ -94,6 +495,8 @@ fn main() {
             steady_length,
             report_mode,
             report_path,
+            label,
+            run_manifest,
         } => {
             let mut log_files_paths: Vec<String> = Vec::new();
             for entry in glob(&log_files_glob).expect("Failed to read glob pattern") {
@@ -106,19 +509,29 @@ fn main() {
                     Err(e) => println!("{:?}", e),
                 }
             }
+            let run_manifest = reporting::find_run_manifest(run_manifest, &log_files_paths);
+            let steady_begin =
+                reporting::resolve_steady_begin(steady_begin_offset, run_manifest.as_ref());
             reporting::build_reports(
                 &log_files_paths,
-                steady_begin_offset,
+                steady_begin,
                 steady_length,
                 report_mode,
                 report_path,
+                label,
+                run_manifest
+                    .map(|manifest| manifest.non_compliance)
+                    .unwrap_or_default(),
             );
         }
         RunMode::SampleLogFiles {
             terminal_count,
             iter_count,
+            seed,
+            start_time_ms,
+            naming,
         } => {
-            generator::gen_sample_data(terminal_count, iter_count);
+            generator::gen_sample_data(terminal_count, iter_count, seed, start_time_ms, naming);
         }
     }
 }
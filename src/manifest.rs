@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::generator::GenCfgParams;
+use crate::launch::LAUNCH_LOG_DIR;
+use crate::rampup::RampUpPlan;
+use crate::sharding::HostAssignment;
+use crate::util::Error;
+
+/// Name of the run manifest file written next to the terminal configs
+pub const MANIFEST_FILE_NAME: &str = "manifest.yaml";
+
+/// Run metadata written by `generate` for the tools consuming its output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RunManifest {
+    /// Version of the tool the configs were generated with
+    pub tool_version: String,
+    /// Parameters `generate` was run with
+    pub params: GenCfgParams,
+    /// Seed the configs were generated with, drawn at random unless given in the parameters
+    pub seed: u64,
+    /// Number of terminals over all the warehouses
+    pub terminal_count: u64,
+    /// Terminal count of every warehouse, the ones without terminals included. Empty for the manifests written before it
+    #[serde(default)]
+    pub terminals_per_warehouse: BTreeMap<u32, u32>,
+    pub ramp_up: RampUpPlan,
+    /// TPC-C rules the run breaks, empty for compliant runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub non_compliance: Vec<String>,
+    /// Start delay of the terminal starting last. Absent in the manifests written before it,
+    /// their suggested offset counts from the start of the last terminal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_start_delay_ms: Option<u64>,
+    /// Offset from the start of the run `test-report` should begin the steady interval at,
+    /// see `suggested_steady_begin_offset_ms`
+    pub suggested_steady_begin_offset_ms: u64,
+    /// Terminals of every driver host, empty unless the run is spread over driver hosts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub driver_hosts: Vec<HostAssignment>,
+    /// SHA-256 of every generated file by its path relative to the manifest
+    pub files: BTreeMap<String, String>,
+}
+
+/// Differences between a config directory and its manifest
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl RunManifest {
+    pub fn from_file(path: &Path) -> Result<RunManifest, Error> {
+        let content = fs::read_to_string(path).map_err(|e| Error {
+            err_msg: format!("Error reading run manifest {:?}: {}", path, e),
+        })?;
+        serde_yaml::from_str(&content).map_err(|e| Error {
+            err_msg: format!("Run manifest {:?} has an incorrect format: {}", path, e),
+        })
+    }
+
+    pub fn write(&self, dir: &Path) {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let str = serde_yaml::to_string(self).expect("Unsupported manifest format");
+        fs::write(&path, &str)
+            .unwrap_or_else(|e| panic!("Error writing run manifest {:?}: {}", &path, e));
+    }
+
+    /// Check the files of the directory against the checksums of the manifest
+    pub fn verify(&self, dir: &Path) -> Result<VerifyReport, Error> {
+        let mut report = VerifyReport::default();
+        for (name, checksum) in &self.files {
+            let path = dir.join(name);
+            if !path.is_file() {
+                report.missing.push(name.clone());
+            } else if file_sha256(&path).map_err(|e| Error {
+                err_msg: format!("Error reading {:?}: {}", &path, e),
+            })? != *checksum
+            {
+                report.modified.push(name.clone());
+            }
+        }
+        let mut found = Vec::new();
+        list_files(dir, "", &mut found).map_err(|e| Error {
+            err_msg: format!("Error listing config directory {:?}: {}", dir, e),
+        })?;
+        report.extra = found
+            .into_iter()
+            .filter(|name| {
+                name != MANIFEST_FILE_NAME && !self.files.contains_key(name) && !self.is_log(name)
+            })
+            .collect();
+        report.extra.sort();
+        Ok(report)
+    }
+
+    /// Whether the file is under the log directory the launched emulators write to,
+    /// the one of the run or of a driver host
+    fn is_log(&self, name: &str) -> bool {
+        let log_dir_prefix = format!("{}/", LAUNCH_LOG_DIR);
+        name.starts_with(&log_dir_prefix)
+            || self.driver_hosts.iter().any(|assignment| {
+                name.starts_with(&format!("{}/{}", assignment.host, log_dir_prefix))
+            })
+    }
+}
+
+/// Offset from the start of the run the steady interval should begin at: the start delay of the last terminal
+/// plus one expected deck cycle, so every terminal runs a full deck before the measurement
+pub fn suggested_steady_begin_offset_ms(
+    ramp_up: &RampUpPlan,
+    term_total: u64,
+    wh_total: u64,
+    deck_cycle_time_ms: u64,
+) -> u64 {
+    ramp_up.last_start_delay_ms(term_total, wh_total) + deck_cycle_time_ms
+}
+
+/// Hex-encoded SHA-256 of the file content
+pub fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.result()))
+}
+
+/// Paths of all the files under the directory, relative to it and separated by '/'
+pub fn list_files(dir: &Path, prefix: &str, found: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_files(&entry.path(), &format!("{}/", name), found)?;
+        } else {
+            found.push(name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rampup::RampUpMode;
+    use std::time::Duration;
+
+    const CYCLE_MS: u64 = 500_000;
+
+    fn offset_ms(mode: RampUpMode, steps: u32, term_total: u64, wh_total: u64) -> u64 {
+        let plan = RampUpPlan::new(mode, Duration::from_secs(60), steps);
+        suggested_steady_begin_offset_ms(&plan, term_total, wh_total, CYCLE_MS)
+    }
+
+    #[test]
+    fn offset_without_ramp_up_is_one_cycle() {
+        assert_eq!(offset_ms(RampUpMode::None, 5, 100, 10), CYCLE_MS);
+    }
+
+    #[test]
+    fn offset_covers_linear_ramp_up() {
+        assert_eq!(offset_ms(RampUpMode::Linear, 5, 100, 10), 60_000 + CYCLE_MS);
+        assert_eq!(offset_ms(RampUpMode::Linear, 5, 1, 1), CYCLE_MS);
+    }
+
+    #[test]
+    fn offset_covers_stepped_ramp_up() {
+        assert_eq!(
+            offset_ms(RampUpMode::Stepped, 5, 100, 10),
+            60_000 + CYCLE_MS
+        );
+        assert_eq!(offset_ms(RampUpMode::Stepped, 1, 100, 10), CYCLE_MS);
+        // 3 terminals fall into the steps 0, 1 and 3 of 5
+        assert_eq!(offset_ms(RampUpMode::Stepped, 5, 3, 1), 45_000 + CYCLE_MS);
+    }
+
+    #[test]
+    fn offset_covers_waves_ramp_up() {
+        assert_eq!(offset_ms(RampUpMode::Waves, 5, 100, 10), 60_000 + CYCLE_MS);
+        assert_eq!(offset_ms(RampUpMode::Waves, 5, 10, 1), CYCLE_MS);
+    }
+}
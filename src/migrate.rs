@@ -0,0 +1,221 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::cfg::*;
+use crate::launch::COMPOSE_FILE_NAME;
+use crate::manifest::{file_sha256, list_files, RunManifest, MANIFEST_FILE_NAME};
+
+const INDEX_FILE_SUFFIX: &str = ".index.yaml";
+
+/// Upgrade every terminal config file and bundle under the directory to the current schema version in place.
+/// The checksums of the run manifest, if any, are updated for the files rewritten
+pub fn migrate_cfg_dir(dir: &Path) {
+    let mut names = Vec::new();
+    list_files(dir, "", &mut names)
+        .unwrap_or_else(|e| panic!("Error listing config directory {:?}: {}", dir, e));
+
+    let index_names: Vec<&String> = names
+        .iter()
+        .filter(|name| name.ends_with(INDEX_FILE_SUFFIX))
+        .collect();
+    let mut skipped: BTreeSet<String> = index_names.iter().map(|name| name.to_string()).collect();
+    skipped.insert(MANIFEST_FILE_NAME.to_string());
+    let mut rewritten: Vec<String> = Vec::new();
+    index_names.iter().for_each(|index_name| {
+        let bundle_name = migrate_bundle(dir, index_name);
+        skipped.insert(bundle_name.clone());
+        rewritten.push(bundle_name);
+        rewritten.push(index_name.to_string());
+    });
+
+    let cfg_names: Vec<&String> = names
+        .iter()
+        .filter(|name| !skipped.contains(*name))
+        .filter(|name| {
+            Path::new(name).file_name().and_then(|n| n.to_str()) != Some(COMPOSE_FILE_NAME)
+        })
+        .filter(|name| {
+            Path::new(name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(CfgFormat::from_file_extension)
+                .is_some()
+        })
+        .collect();
+    let migrated: Vec<String> = cfg_names
+        .par_iter()
+        .filter(|name| migrate_cfg_file(&dir.join(name)))
+        .map(|name| name.to_string())
+        .collect();
+    println!(
+        "Migrated {} of {} config files to the schema version {}, rewrote {} bundles",
+        migrated.len(),
+        cfg_names.len(),
+        CFG_SCHEMA_VERSION,
+        index_names.len()
+    );
+    rewritten.extend(migrated);
+
+    let manifest_path = dir.join(MANIFEST_FILE_NAME);
+    if !manifest_path.exists() || rewritten.is_empty() {
+        return;
+    }
+    match RunManifest::from_file(&manifest_path) {
+        Ok(mut manifest) => {
+            rewritten.iter().for_each(|name| {
+                if let Some(checksum) = manifest.files.get_mut(name) {
+                    *checksum = file_sha256(&dir.join(name))
+                        .unwrap_or_else(|e| panic!("Error reading migrated file {}: {}", name, e));
+                }
+            });
+            manifest.write(dir);
+        }
+        Err(e) => println!("Checksums not updated: {}", e.err_msg),
+    }
+}
+
+/// Rewrite a single config file if its schema is outdated, returns true if it was
+fn migrate_cfg_file(path: &Path) -> bool {
+    let mut cfgz = read_cfg(path).unwrap_or_else(|e| panic!("{}", e.err_msg));
+    if !cfgz.migrate() {
+        return false;
+    }
+    let format =
+        CfgFormat::from_file_extension(path.extension().unwrap().to_str().unwrap()).unwrap();
+    let doc = format
+        .serialize_cfg(&cfgz)
+        .unwrap_or_else(|e| panic!("Error serializing cfg as {}: {}", format, e));
+    fs::write(path, &doc).unwrap_or_else(|e| panic!("Error writing cfg file {:?}: {}", path, e));
+    true
+}
+
+/// Rewrite every config of a bundle along with the offsets of its index, returns the bundle path relative to `dir`
+fn migrate_bundle(dir: &Path, index_name: &str) -> String {
+    let index_path = dir.join(index_name);
+    let bundle_dir = index_path.parent().unwrap();
+    let mut index = BundleIndex::from_file(&index_path).unwrap_or_else(|e| panic!("{}", e.err_msg));
+    let bundle_path = bundle_dir.join(&index.bundle_file_name);
+    let tmp_path = bundle_dir.join(format!("{}.tmp", index.bundle_file_name));
+    let mut wtr = BufWriter::new(
+        File::create(&tmp_path)
+            .unwrap_or_else(|e| panic!("Error creating bundle file {:?}: {}", &tmp_path, e)),
+    );
+    let mut offset = 0;
+    for i in 0..index.terminals.len() {
+        let (w, t) = (
+            index.terminals[i].home_warehouse_id,
+            index.terminals[i].this_terminal_id,
+        );
+        let mut cfgz = index
+            .read_cfg(bundle_dir, w, t)
+            .unwrap_or_else(|e| panic!("{}", e.err_msg));
+        cfgz.migrate();
+        let doc = index
+            .format
+            .serialize_cfg(&cfgz)
+            .unwrap_or_else(|e| panic!("Error serializing cfg as {}: {}", index.format, e));
+        wtr.write_all(&doc)
+            .unwrap_or_else(|e| panic!("Error writing bundle file {:?}: {}", &tmp_path, e));
+        let entry = &mut index.terminals[i];
+        entry.offset = offset;
+        entry.length = doc.len() as u64;
+        offset += doc.len() as u64;
+    }
+    wtr.flush()
+        .unwrap_or_else(|e| panic!("Error writing bundle file {:?}: {}", &tmp_path, e));
+    fs::rename(&tmp_path, &bundle_path)
+        .unwrap_or_else(|e| panic!("Error replacing bundle file {:?}: {}", &bundle_path, e));
+    index.write(&index_path);
+
+    let prefix = &index_name[..index_name.len() - index_name.rsplit('/').next().unwrap().len()];
+    format!("{}{}", prefix, index.bundle_file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config as written before the schema version was recorded: no header, no district
+    fn legacy_cfg(w: u32, t: u32) -> String {
+        format!(
+            "---\nhome_warehouse_id: {}\nthis_terminal_id: {}\ntransactions_to_run:\n  \
+             - type: NewOrder\n    keying_time_ms: 18000\n    think_time_ms: 12000\n    is_rbk: false\n  \
+             - type: Payment\n    keying_time_ms: 3000\n    think_time_ms: 12000\n    is_rbk: true\n",
+            w, t
+        )
+    }
+
+    #[test]
+    fn legacy_configs_are_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("host1")).unwrap();
+        fs::write(dir.path().join("W1_T3.cfg"), legacy_cfg(1, 3)).unwrap();
+        fs::write(dir.path().join("host1/W2_T13.cfg"), legacy_cfg(2, 13)).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a config").unwrap();
+
+        migrate_cfg_dir(dir.path());
+
+        for (name, t, district_id) in &[("W1_T3.cfg", 3, 3), ("host1/W2_T13.cfg", 13, 3)] {
+            let cfgz = read_cfg(&dir.path().join(name)).unwrap();
+            assert_eq!(cfgz.header.schema_version, CFG_SCHEMA_VERSION);
+            assert_eq!(cfgz.this_terminal_id, *t);
+            assert_eq!(cfgz.district_id, Some(*district_id));
+            assert!(cfgz.header.nurand.is_none());
+            assert!(cfgz.header.time_scale.is_none());
+            assert!(cfgz.header.non_compliance.is_empty());
+            assert!(cfgz.header.arrival.is_none());
+            assert_eq!(cfgz.start_delay_ms, 0);
+            assert_eq!(cfgz.seed, None);
+            assert!(cfgz.think_time_distribution.is_empty());
+            assert_eq!(cfgz.transactions_to_run.len(), 2);
+            assert!(cfgz.transactions_to_run[1].is_rbk);
+            assert!(cfgz
+                .transactions_to_run
+                .iter()
+                .all(|tx| tx.input.is_none() && tx.start_offset_ms.is_none()));
+        }
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "not a config"
+        );
+
+        // Up to date configs are left as they are
+        let migrated = fs::read(dir.path().join("W1_T3.cfg")).unwrap();
+        migrate_cfg_dir(dir.path());
+        assert_eq!(fs::read(dir.path().join("W1_T3.cfg")).unwrap(), migrated);
+    }
+
+    #[test]
+    fn newer_configs_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("W1_T1.cfg");
+        fs::write(
+            &path,
+            format!(
+                "---\nheader:\n  schema_version: {}\n{}",
+                CFG_SCHEMA_VERSION + 1,
+                &legacy_cfg(1, 1)[4..]
+            ),
+        )
+        .unwrap();
+        let err = read_cfg(&path).unwrap_err().err_msg;
+        assert!(
+            err.contains(&format!(
+                "schema version {} is newer than the supported version {}",
+                CFG_SCHEMA_VERSION + 1,
+                CFG_SCHEMA_VERSION
+            )),
+            "{}",
+            err
+        );
+        let err = std::panic::catch_unwind(|| migrate_cfg_dir(dir.path())).unwrap_err();
+        assert!(err.downcast_ref::<String>().unwrap().contains("is newer"));
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains(&format!("schema_version: {}", CFG_SCHEMA_VERSION + 1)));
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use crate::util::Error;
+
+pub const DEFAULT_CFG_DIR: &str = "term-config/{ts}";
+pub const DEFAULT_CFG_NAME: &str = "{ts}_W{w}_T{t}";
+pub const DEFAULT_BUNDLE_NAME: &str = "{ts}_bundle";
+pub const DEFAULT_SAMPLE_LOG_DIR: &str = "sample-logs/{ts}";
+pub const DEFAULT_SAMPLE_LOG_NAME: &str = "{ts}_T{t}";
+pub const DEFAULT_REPORT_DIR: &str = "test-reports/{ts}";
+pub const DEFAULT_POPULATION_DIR: &str = "population/{ts}";
+
+/// Timestamp format of the `{ts}` placeholder
+pub const TS_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+const PLACEHOLDERS: [&str; 4] = ["w", "t", "ts", "label"];
+
+/// Placeholders of the directory templates, a directory holds the artifacts of many terminals
+const DIR_PLACEHOLDERS: [&str; 2] = ["ts", "label"];
+
+/// Placeholders of the launch command template, the name ones along with the driver host,
+/// the config path and the log path
+const COMMAND_PLACEHOLDERS: [&str; 7] = ["w", "t", "ts", "label", "host", "cfg", "log"];
+
+// Output location and file naming of the generated artifacts.
+// Not a doc comment: it would replace the description of the subcommands flattening the struct
+#[derive(StructOpt, Serialize, Deserialize, Debug, Clone)]
+pub struct OutputNaming {
+    /// Output directory template, e.g. `out/{label}`. Accepts the `{ts}` and `{label}` placeholders,
+    /// defaults to a timestamped directory
+    #[structopt(short = "o", long, parse(try_from_str = parse_dir_template))]
+    pub output_dir: Option<String>,
+
+    /// File name template without the extension, e.g. `{label}_W{w}_T{t}`.
+    /// Accepts the `{w}`, `{t}`, `{ts}` and `{label}` placeholders
+    #[structopt(long, parse(try_from_str = parse_template))]
+    pub name_template: Option<String>,
+
+    /// Run label substituted for the `{label}` placeholder
+    #[structopt(long)]
+    pub label: Option<String>,
+}
+
+/// Values of the placeholders, the ones not applicable to an artifact are left unset
+#[derive(Default)]
+pub struct NameVars<'a> {
+    pub ts: &'a str,
+    pub label: Option<&'a str>,
+    pub w: Option<u32>,
+    pub t: Option<u32>,
+    pub host: Option<&'a str>,
+    pub cfg: Option<&'a str>,
+    pub log: Option<&'a str>,
+}
+
+/// Check the template has only known and well-formed placeholders
+pub fn parse_template(s: &str) -> Result<String, Error> {
+    placeholders(s, &PLACEHOLDERS)?;
+    Ok(s.to_string())
+}
+
+/// Check the directory template has only the placeholders shared by all of its artifacts
+pub fn parse_dir_template(s: &str) -> Result<String, Error> {
+    placeholders(s, &DIR_PLACEHOLDERS)?;
+    Ok(s.to_string())
+}
+
+/// Check the launch command template has only known and well-formed placeholders
+pub fn parse_command_template(s: &str) -> Result<String, Error> {
+    placeholders(s, &COMMAND_PLACEHOLDERS)?;
+    Ok(s.to_string())
+}
+
+/// Check the template has every placeholder required to keep the file names distinct
+pub fn require_placeholders(template: &str, required: &[&str]) -> Result<(), Error> {
+    let found = placeholders(template, &COMMAND_PLACEHOLDERS)?;
+    match required.iter().find(|p| !found.contains(p)) {
+        Some(p) => Err(Error {
+            err_msg: format!(
+                "Name template '{}' must contain the {{{}}} placeholder",
+                template, p
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn render(template: &str, vars: &NameVars) -> Result<String, Error> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered += &rest[..start];
+        let end = start + rest[start..].find('}').unwrap();
+        let name = &rest[start + 1..end];
+        let value = match name {
+            "w" => vars.w.map(|w| w.to_string()),
+            "t" => vars.t.map(|t| t.to_string()),
+            "ts" => Some(vars.ts.to_string()),
+            "label" => vars.label.map(|label| label.to_string()),
+            "host" => vars.host.map(|host| host.to_string()),
+            "cfg" => vars.cfg.map(|cfg| cfg.to_string()),
+            "log" => vars.log.map(|log| log.to_string()),
+            _ => None,
+        };
+        rendered += &value.ok_or_else(|| Error {
+            err_msg: match name {
+                "label" => format!(
+                    "Placeholder {{label}} of the template '{}' requires --label",
+                    template
+                ),
+                _ => format!(
+                    "Placeholder {{{}}} of the template '{}' has no value here",
+                    name, template
+                ),
+            },
+        })?;
+        rest = &rest[end + 1..];
+    }
+    rendered += rest;
+    Ok(rendered)
+}
+
+fn placeholders<'a>(template: &'a str, allowed: &[&str]) -> Result<Vec<&'a str>, Error> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| Error {
+            err_msg: format!("Template '{}' has an unclosed placeholder", template),
+        })? + start;
+        let name = &rest[start + 1..end];
+        if !allowed.contains(&name) {
+            return Err(Error {
+                err_msg: format!(
+                    "Template '{}' has an unknown placeholder {{{}}}, expected one of {{{}}}",
+                    template,
+                    name,
+                    allowed.join("}, {")
+                ),
+            });
+        }
+        found.push(name);
+        rest = &rest[end + 1..];
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_templates_reject_terminal_placeholders() {
+        assert_eq!(
+            parse_dir_template("out/{label}/{ts}").unwrap(),
+            "out/{label}/{ts}"
+        );
+        assert!(parse_dir_template(DEFAULT_CFG_DIR).is_ok());
+        for template in &["out/{w}", "out/W{w}_T{t}", "out/{host}"] {
+            let err = parse_dir_template(template).unwrap_err();
+            assert!(
+                err.err_msg.contains("unknown placeholder") && err.err_msg.contains(template),
+                "{}",
+                err.err_msg
+            );
+        }
+        assert!(parse_template("{label}_W{w}_T{t}").is_ok());
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use crate::cfg::TransactionType;
+use crate::generator::tx_breakdown;
+use crate::profile::WorkloadProfile;
+use crate::tpcc::{DISTRICTS_PER_WAREHOUSE, MAX_TPMC_PER_WAREHOUSE, MIN_MEASUREMENT_INTERVAL_MS};
+use crate::util::Error;
+
+/// What to size a run for
+pub enum PlanTarget {
+    Tpmc(f64),
+    WarehouseCount(u32),
+}
+
+/// Expected throughput of the terminals running a deck
+#[derive(Debug, PartialEq)]
+pub struct Throughput {
+    pub terminal_tpmc: f64,
+    /// Throughput of the 10 terminals of a warehouse as the profile allows it
+    pub profile_warehouse_tpmc: f64,
+    /// The same capped at the TPC-C maximum
+    pub warehouse_tpmc: f64,
+}
+
+/// Throughput of terminals running `new_order_count` NewOrders per deck cycle of `deck_cycle_time_ms`
+pub fn expected_throughput(new_order_count: u32, deck_cycle_time_ms: u64) -> Throughput {
+    let terminal_tpmc = new_order_count as f64 * 60_000. / deck_cycle_time_ms as f64;
+    let profile_warehouse_tpmc = terminal_tpmc * DISTRICTS_PER_WAREHOUSE as f64;
+    Throughput {
+        terminal_tpmc,
+        profile_warehouse_tpmc,
+        warehouse_tpmc: profile_warehouse_tpmc.min(MAX_TPMC_PER_WAREHOUSE),
+    }
+}
+
+/// Warehouses needed for the target tpmC at the throughput of a warehouse.
+/// The target is usually a multiple of the throughput typed in by hand, the rounding error is ignored
+pub fn warehouses_for(tpmc: f64, warehouse_tpmc: f64) -> u32 {
+    (tpmc / warehouse_tpmc - 1e-9).ceil().max(1.) as u32
+}
+
+/// Range of the warehouse counts the target tpmC requires: the fewest at the TPC-C maximum per warehouse
+/// and the count at the expected throughput of a warehouse, the run is sized with the latter
+pub fn warehouse_range(tpmc: f64, throughput: &Throughput) -> (u32, u32) {
+    (
+        warehouses_for(tpmc, MAX_TPMC_PER_WAREHOUSE),
+        warehouses_for(tpmc, throughput.warehouse_tpmc),
+    )
+}
+
+/// Run duration assumed when none is given: a deck cycle of warm-up and the minimum measurement interval,
+/// rounded up to whole minutes. The planned command line has no ramp-up to cover
+pub fn default_duration(deck_cycle_time_ms: u64) -> Duration {
+    let minute_ms = 60_000;
+    Duration::from_millis(
+        (deck_cycle_time_ms + MIN_MEASUREMENT_INTERVAL_MS).div_ceil(minute_ms) * minute_ms,
+    )
+}
+
+/// Size a run from a target tpmC or a warehouse count with the expected times of the profile
+/// and print the matching `generate` command line.
+/// `profile_path` is passed on to the command line, the built-in profile is used if omitted
+pub fn plan_run(
+    target: PlanTarget,
+    first_warehouse_id: u32,
+    duration: Option<Duration>,
+    profile_path: Option<String>,
+) -> Result<(), Error> {
+    let profile = match &profile_path {
+        Some(path) => WorkloadProfile::from_file(path)?,
+        None => WorkloadProfile::default(),
+    };
+    let deck_size = profile.deck_size;
+    let tx_bkdwn = tx_breakdown(deck_size, &profile);
+
+    println!(
+        "{:<12} {:>6} {:>10} {:>10} {:>10} {:>10}",
+        "Type", "Cards", "Keying", "Think avg", "RT", "Cycle"
+    );
+    let mut deck_cycle_time_ms = 0;
+    TransactionType::iter().for_each(|typ| {
+        let dflts = tx_bkdwn.get(typ).unwrap();
+        let cycle_time_ms =
+            dflts.keying_time_ms + dflts.think_time_mean_ms + profile.get(typ).expected_rt_ms;
+        deck_cycle_time_ms += dflts.tx_count as u64 * cycle_time_ms as u64;
+        println!(
+            "{:<12} {:>6} {:>10} {:>10} {:>10} {:>10}",
+            format!("{:?}", typ),
+            dflts.tx_count,
+            dflts.keying_time_ms,
+            dflts.think_time_mean_ms,
+            profile.get(typ).expected_rt_ms,
+            cycle_time_ms
+        );
+    });
+    if deck_cycle_time_ms == 0 {
+        return Err(Error {
+            err_msg: "Deck cycle time of the profile is zero, the throughput is unbounded"
+                .to_string(),
+        });
+    }
+    let new_order_count = tx_bkdwn.get(&TransactionType::NewOrder).unwrap().tx_count;
+    let throughput = expected_throughput(new_order_count, deck_cycle_time_ms);
+    let Throughput {
+        terminal_tpmc,
+        profile_warehouse_tpmc,
+        warehouse_tpmc,
+    } = throughput;
+
+    let mut warehouse_range_line = None;
+    let warehouse_count = match target {
+        PlanTarget::Tpmc(tpmc) if tpmc <= 0. => {
+            return Err(Error {
+                err_msg: format!("Target tpmC must be more than 0, got {}", tpmc),
+            })
+        }
+        PlanTarget::Tpmc(tpmc) => {
+            let (min_count, max_count) = warehouse_range(tpmc, &throughput);
+            warehouse_range_line = Some(format!(
+                "Required warehouses for {} tpmC: {} at the TPC-C maximum of {} tpmC per warehouse to {} at the expected {:.3}",
+                tpmc, min_count, MAX_TPMC_PER_WAREHOUSE, max_count, warehouse_tpmc
+            ));
+            max_count
+        }
+        PlanTarget::WarehouseCount(0) => {
+            return Err(Error {
+                err_msg: "Warehouse count must be more than 0".to_string(),
+            })
+        }
+        PlanTarget::WarehouseCount(count) => count,
+    };
+    let last_warehouse_id = first_warehouse_id + warehouse_count - 1;
+    let warehouse_ids = match warehouse_count {
+        1 => first_warehouse_id.to_string(),
+        _ => format!("{}..{}", first_warehouse_id, last_warehouse_id),
+    };
+
+    println!(
+        "Transactions per deck: {}, NewOrder {}",
+        deck_size, new_order_count
+    );
+    println!(
+        "Expected terminal cycle time: {:.0} ms per transaction, {} per deck",
+        deck_cycle_time_ms as f64 / deck_size as f64,
+        humantime::format_duration(Duration::from_millis(deck_cycle_time_ms))
+    );
+    println!(
+        "Expected throughput: {:.3} tpmC per terminal, {:.3} tpmC per warehouse{}",
+        terminal_tpmc,
+        warehouse_tpmc,
+        match profile_warehouse_tpmc > MAX_TPMC_PER_WAREHOUSE {
+            true => format!(
+                " (the profile allows {:.3}, capped at the TPC-C maximum)",
+                profile_warehouse_tpmc
+            ),
+            false => String::new(),
+        }
+    );
+    if let Some(line) = warehouse_range_line {
+        println!("{}", line);
+    }
+    println!(
+        "Warehouses: {} ({}), terminals: {} per warehouse, {} in total",
+        warehouse_count,
+        warehouse_ids,
+        DISTRICTS_PER_WAREHOUSE,
+        warehouse_count * DISTRICTS_PER_WAREHOUSE
+    );
+    println!(
+        "Expected tpmC: {:.2}",
+        warehouse_count as f64 * warehouse_tpmc
+    );
+    let duration = match duration {
+        Some(duration) => {
+            println!("Duration: {}", humantime::format_duration(duration));
+            duration
+        }
+        None => {
+            let duration = default_duration(deck_cycle_time_ms);
+            println!(
+                "Duration: {}, assumed to cover a deck cycle of warm-up and the {} minimum measurement interval, rounded up. Set it with -d",
+                humantime::format_duration(duration),
+                humantime::format_duration(Duration::from_millis(MIN_MEASUREMENT_INTERVAL_MS))
+            );
+            duration
+        }
+    };
+
+    let mut command = format!(
+        "cli_gen generate -w {} -t {} -d \"{}\"",
+        warehouse_ids,
+        DISTRICTS_PER_WAREHOUSE,
+        humantime::format_duration(duration)
+    );
+    if let Some(path) = &profile_path {
+        command += &format!(" -p \"{}\"", path);
+    }
+    let violations = profile.violations();
+    if !violations.is_empty() {
+        println!(
+            "The profile breaks the TPC-C rules:\n{}",
+            violations.join("\n")
+        );
+        command += " --non-compliant";
+    }
+    println!("{}", command);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throughput_follows_the_deck_cycle() {
+        // 10 NewOrders per 10 minute cycle
+        let throughput = expected_throughput(10, 600_000);
+        assert_eq!(throughput.terminal_tpmc, 1.);
+        assert_eq!(throughput.profile_warehouse_tpmc, 10.);
+        assert_eq!(throughput.warehouse_tpmc, 10.);
+        // Faster terminals are capped at the TPC-C maximum
+        let throughput = expected_throughput(10, 300_000);
+        assert_eq!(throughput.profile_warehouse_tpmc, 20.);
+        assert_eq!(throughput.warehouse_tpmc, MAX_TPMC_PER_WAREHOUSE);
+    }
+
+    #[test]
+    fn warehouse_range_spans_the_cap_and_the_expected_throughput() {
+        let slow = expected_throughput(10, 600_000);
+        assert_eq!(warehouse_range(1000., &slow), (78, 100));
+        assert_eq!(warehouse_range(1001., &slow), (78, 101));
+        assert_eq!(warehouse_range(1., &slow), (1, 1));
+        let capped = expected_throughput(10, 300_000);
+        assert_eq!(warehouse_range(1000., &capped), (78, 78));
+    }
+
+    #[test]
+    fn default_duration_covers_warm_up_and_measurement() {
+        let deck_cycle_time_ms = WorkloadProfile::default().deck_cycle_time_ms();
+        assert_eq!(deck_cycle_time_ms, 485_400);
+        assert_eq!(
+            default_duration(deck_cycle_time_ms),
+            Duration::from_secs((2 * 60 + 9) * 60)
+        );
+        // Whole minutes are kept as they are
+        assert_eq!(
+            default_duration(60_000),
+            Duration::from_secs((2 * 60 + 1) * 60)
+        );
+    }
+
+    #[test]
+    fn multiples_of_the_cap_need_no_extra_warehouse() {
+        for count in 1..5000 {
+            let tpmc = count as f64 * MAX_TPMC_PER_WAREHOUSE;
+            assert_eq!(warehouses_for(tpmc, MAX_TPMC_PER_WAREHOUSE), count);
+            assert_eq!(
+                warehouses_for(tpmc + 0.01, MAX_TPMC_PER_WAREHOUSE),
+                count + 1
+            );
+        }
+    }
+}
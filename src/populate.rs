@@ -0,0 +1,504 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::generator::{resolve_seed, term_rng};
+use crate::naming::{render, NameVars, DEFAULT_POPULATION_DIR, TS_FORMAT};
+use crate::schema::{write_sql_scripts, SqlDialect};
+use crate::tpcc::*;
+
+/// Name of the file describing a generated population
+pub const POPULATION_FILE_NAME: &str = "population.yaml";
+pub const ITEM_TABLE: &str = "item";
+/// Tables populated per warehouse, in the loading order
+pub const WAREHOUSE_TABLES: [&str; 8] = [
+    "warehouse",
+    "district",
+    "customer",
+    "history",
+    "orders",
+    "new_order",
+    "order_line",
+    "stock",
+];
+
+/// Parameters of a generated population, kept next to the CSV files
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PopulationInfo {
+    pub warehouse_id_list: Vec<u32>,
+    pub seed: u64,
+    /// C_LAST constant the customer last names are generated with.
+    /// Pass it to `generate --c-last-load` so the run-time value differs as required
+    pub c_last_load: u32,
+}
+
+#[derive(Serialize)]
+struct WarehouseRow {
+    w_id: u32,
+    w_name: String,
+    w_street_1: String,
+    w_street_2: String,
+    w_city: String,
+    w_state: String,
+    w_zip: String,
+    w_tax: String,
+    w_ytd: String,
+}
+
+#[derive(Serialize)]
+struct DistrictRow {
+    d_id: u32,
+    d_w_id: u32,
+    d_name: String,
+    d_street_1: String,
+    d_street_2: String,
+    d_city: String,
+    d_state: String,
+    d_zip: String,
+    d_tax: String,
+    d_ytd: String,
+    d_next_o_id: u32,
+}
+
+#[derive(Serialize)]
+struct CustomerRow {
+    c_id: u32,
+    c_d_id: u32,
+    c_w_id: u32,
+    c_first: String,
+    c_middle: String,
+    c_last: String,
+    c_street_1: String,
+    c_street_2: String,
+    c_city: String,
+    c_state: String,
+    c_zip: String,
+    c_phone: String,
+    c_since: String,
+    c_credit: String,
+    c_credit_lim: String,
+    c_discount: String,
+    c_balance: String,
+    c_ytd_payment: String,
+    c_payment_cnt: u32,
+    c_delivery_cnt: u32,
+    c_data: String,
+}
+
+#[derive(Serialize)]
+struct HistoryRow {
+    h_c_id: u32,
+    h_c_d_id: u32,
+    h_c_w_id: u32,
+    h_d_id: u32,
+    h_w_id: u32,
+    h_date: String,
+    h_amount: String,
+    h_data: String,
+}
+
+#[derive(Serialize)]
+struct OrderRow {
+    o_id: u32,
+    o_d_id: u32,
+    o_w_id: u32,
+    o_c_id: u32,
+    o_entry_d: String,
+    o_carrier_id: Option<u32>,
+    o_ol_cnt: u32,
+    o_all_local: u32,
+}
+
+#[derive(Serialize)]
+struct NewOrderRow {
+    no_o_id: u32,
+    no_d_id: u32,
+    no_w_id: u32,
+}
+
+#[derive(Serialize)]
+struct OrderLineRow {
+    ol_o_id: u32,
+    ol_d_id: u32,
+    ol_w_id: u32,
+    ol_number: u32,
+    ol_i_id: u32,
+    ol_supply_w_id: u32,
+    ol_delivery_d: Option<String>,
+    ol_quantity: u32,
+    ol_amount: String,
+    ol_dist_info: String,
+}
+
+#[derive(Serialize)]
+struct ItemRow {
+    i_id: u32,
+    i_im_id: u32,
+    i_name: String,
+    i_price: String,
+    i_data: String,
+}
+
+#[derive(Serialize)]
+struct StockRow {
+    s_i_id: u32,
+    s_w_id: u32,
+    s_quantity: u32,
+    s_dist_01: String,
+    s_dist_02: String,
+    s_dist_03: String,
+    s_dist_04: String,
+    s_dist_05: String,
+    s_dist_06: String,
+    s_dist_07: String,
+    s_dist_08: String,
+    s_dist_09: String,
+    s_dist_10: String,
+    s_ytd: u32,
+    s_order_cnt: u32,
+    s_remote_cnt: u32,
+    s_data: String,
+}
+
+/// Populated address fields: street 1, street 2, city, state and zip
+struct Address(String, String, String, String, String);
+
+/// Generate the initial database population as CSV files, see TPC-C Clause 4.3.3.1.
+/// The ITEM table is written once, the other tables are written per warehouse in parallel
+pub fn populate(
+    warehouse_id_list: Vec<u32>,
+    seed: Option<u64>,
+    c_last_load: Option<u32>,
+    sql_dialects: Vec<SqlDialect>,
+    output_dir: Option<String>,
+    label: Option<String>,
+) {
+    if warehouse_id_list.is_empty() {
+        panic!("Warehouse id list is empty");
+    }
+    if let Some(c) = c_last_load {
+        if c > NURAND_A_C_LAST {
+            panic!(
+                "C_LAST load constant {} must be within [0 .. {}]",
+                c, NURAND_A_C_LAST
+            );
+        }
+    }
+
+    let wh_ids: Vec<u32> = warehouse_id_list
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<u32>>() // distinct values
+        .into_iter()
+        .collect();
+    let seed = resolve_seed(seed);
+    let c_last_load = c_last_load
+        .unwrap_or_else(|| rand_range(&mut StdRng::seed_from_u64(seed), 0, NURAND_A_C_LAST));
+    println!("C_LAST load constant {}", c_last_load);
+
+    let now: DateTime<Local> = Local::now();
+    let ts = now.format(TS_FORMAT).to_string();
+    let date = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    let out_dir = PathBuf::from(
+        render(
+            output_dir.as_deref().unwrap_or(DEFAULT_POPULATION_DIR),
+            &NameVars {
+                ts: &ts,
+                label: label.as_deref(),
+                ..Default::default()
+            },
+        )
+        .unwrap_or_else(|e| panic!("{}", e.err_msg)),
+    );
+    fs::create_dir_all(&out_dir)
+        .unwrap_or_else(|e| panic!("Error creating population directory {:?}: {}", &out_dir, e));
+
+    let info = PopulationInfo {
+        warehouse_id_list: wh_ids.clone(),
+        seed,
+        c_last_load,
+    };
+    fs::write(
+        out_dir.join(POPULATION_FILE_NAME),
+        serde_yaml::to_string(&info).expect("Unsupported population info format"),
+    )
+    .unwrap_or_else(|e| panic!("Error writing {}: {}", POPULATION_FILE_NAME, e));
+
+    write_sql_scripts(&out_dir, &wh_ids, &sql_dialects);
+
+    gen_items(&out_dir, &mut term_rng(seed, 0, 0));
+    wh_ids.par_iter().for_each(|w| {
+        gen_warehouse(&out_dir, &mut term_rng(seed, *w, 0), *w, c_last_load, &date);
+        println!("Warehouse {} populated", w);
+    });
+}
+
+/// Name of the CSV file holding a table, per warehouse unless it is the ITEM table
+pub fn table_file_name(table: &str, w_id: Option<u32>) -> String {
+    match w_id {
+        Some(w_id) => format!("{}_W{}.csv", table, w_id),
+        None => format!("{}.csv", table),
+    }
+}
+
+pub fn gen_items<R: Rng>(out_dir: &Path, rng: &mut R) {
+    let mut wtr = table_writer(out_dir, ITEM_TABLE, None);
+    for i_id in 1..ITEM_COUNT + 1 {
+        wtr.serialize(ItemRow {
+            i_id,
+            i_im_id: rand_range(rng, 1, 10_000),
+            i_name: rand_a_string(rng, 14, 24),
+            i_price: decimal(rand_range(rng, 100, 10_000), 2),
+            i_data: rand_data(rng),
+        })
+        .expect("Error writing an ITEM row");
+    }
+}
+
+pub fn gen_warehouse<R: Rng>(out_dir: &Path, rng: &mut R, w_id: u32, c_last_load: u32, date: &str) {
+    let Address(street_1, street_2, city, state, zip) = rand_address(rng);
+    table_writer(out_dir, "warehouse", Some(w_id))
+        .serialize(WarehouseRow {
+            w_id,
+            w_name: rand_a_string(rng, 6, 10),
+            w_street_1: street_1,
+            w_street_2: street_2,
+            w_city: city,
+            w_state: state,
+            w_zip: zip,
+            w_tax: decimal(rand_range(rng, 0, 2_000), 4),
+            w_ytd: decimal(30_000_000, 2),
+        })
+        .expect("Error writing a WAREHOUSE row");
+
+    let mut stock_wtr = table_writer(out_dir, "stock", Some(w_id));
+    for s_i_id in 1..STOCK_PER_WAREHOUSE + 1 {
+        stock_wtr
+            .serialize(StockRow {
+                s_i_id,
+                s_w_id: w_id,
+                s_quantity: rand_range(rng, 10, 100),
+                s_dist_01: rand_a_string(rng, 24, 24),
+                s_dist_02: rand_a_string(rng, 24, 24),
+                s_dist_03: rand_a_string(rng, 24, 24),
+                s_dist_04: rand_a_string(rng, 24, 24),
+                s_dist_05: rand_a_string(rng, 24, 24),
+                s_dist_06: rand_a_string(rng, 24, 24),
+                s_dist_07: rand_a_string(rng, 24, 24),
+                s_dist_08: rand_a_string(rng, 24, 24),
+                s_dist_09: rand_a_string(rng, 24, 24),
+                s_dist_10: rand_a_string(rng, 24, 24),
+                s_ytd: 0,
+                s_order_cnt: 0,
+                s_remote_cnt: 0,
+                s_data: rand_data(rng),
+            })
+            .expect("Error writing a STOCK row");
+    }
+
+    let mut district_wtr = table_writer(out_dir, "district", Some(w_id));
+    let mut customer_wtr = table_writer(out_dir, "customer", Some(w_id));
+    let mut history_wtr = table_writer(out_dir, "history", Some(w_id));
+    let mut order_wtr = table_writer(out_dir, "orders", Some(w_id));
+    let mut new_order_wtr = table_writer(out_dir, "new_order", Some(w_id));
+    let mut order_line_wtr = table_writer(out_dir, "order_line", Some(w_id));
+    for d_id in 1..DISTRICTS_PER_WAREHOUSE + 1 {
+        let Address(street_1, street_2, city, state, zip) = rand_address(rng);
+        district_wtr
+            .serialize(DistrictRow {
+                d_id,
+                d_w_id: w_id,
+                d_name: rand_a_string(rng, 6, 10),
+                d_street_1: street_1,
+                d_street_2: street_2,
+                d_city: city,
+                d_state: state,
+                d_zip: zip,
+                d_tax: decimal(rand_range(rng, 0, 2_000), 4),
+                d_ytd: decimal(3_000_000, 2),
+                d_next_o_id: ORDERS_PER_DISTRICT + 1,
+            })
+            .expect("Error writing a DISTRICT row");
+
+        for c_id in 1..CUSTOMERS_PER_DISTRICT + 1 {
+            // The first 1000 customers get every possible last name once
+            let c_last = if c_id <= 1_000 {
+                last_name(c_id - 1)
+            } else {
+                last_name(nurand(rng, NURAND_A_C_LAST, 0, 999, c_last_load))
+            };
+            let Address(street_1, street_2, city, state, zip) = rand_address(rng);
+            customer_wtr
+                .serialize(CustomerRow {
+                    c_id,
+                    c_d_id: d_id,
+                    c_w_id: w_id,
+                    c_first: rand_a_string(rng, 8, 16),
+                    c_middle: "OE".to_string(),
+                    c_last,
+                    c_street_1: street_1,
+                    c_street_2: street_2,
+                    c_city: city,
+                    c_state: state,
+                    c_zip: zip,
+                    c_phone: rand_n_string(rng, 16, 16),
+                    c_since: date.to_string(),
+                    c_credit: if rand_range(rng, 1, 100) <= 10 {
+                        "BC".to_string()
+                    } else {
+                        "GC".to_string()
+                    },
+                    c_credit_lim: decimal(5_000_000, 2),
+                    c_discount: decimal(rand_range(rng, 0, 5_000), 4),
+                    c_balance: "-10.00".to_string(),
+                    c_ytd_payment: decimal(1_000, 2),
+                    c_payment_cnt: 1,
+                    c_delivery_cnt: 0,
+                    c_data: rand_a_string(rng, 300, 500),
+                })
+                .expect("Error writing a CUSTOMER row");
+            history_wtr
+                .serialize(HistoryRow {
+                    h_c_id: c_id,
+                    h_c_d_id: d_id,
+                    h_c_w_id: w_id,
+                    h_d_id: d_id,
+                    h_w_id: w_id,
+                    h_date: date.to_string(),
+                    h_amount: decimal(1_000, 2),
+                    h_data: rand_a_string(rng, 12, 24),
+                })
+                .expect("Error writing a HISTORY row");
+        }
+
+        // Order customers are a random permutation of all the district's customers
+        let mut o_c_ids: Vec<u32> = (1..CUSTOMERS_PER_DISTRICT + 1).collect();
+        o_c_ids.shuffle(rng);
+        for o_id in 1..ORDERS_PER_DISTRICT + 1 {
+            let is_delivered = o_id < FIRST_NEW_ORDER_ID;
+            let o_ol_cnt = rand_range(rng, MIN_ORDER_LINE_COUNT, MAX_ORDER_LINE_COUNT);
+            order_wtr
+                .serialize(OrderRow {
+                    o_id,
+                    o_d_id: d_id,
+                    o_w_id: w_id,
+                    o_c_id: o_c_ids[(o_id - 1) as usize],
+                    o_entry_d: date.to_string(),
+                    o_carrier_id: if is_delivered {
+                        Some(rand_range(rng, 1, MAX_CARRIER_ID))
+                    } else {
+                        None
+                    },
+                    o_ol_cnt,
+                    o_all_local: 1,
+                })
+                .expect("Error writing an ORDER row");
+            for ol_number in 1..o_ol_cnt + 1 {
+                order_line_wtr
+                    .serialize(OrderLineRow {
+                        ol_o_id: o_id,
+                        ol_d_id: d_id,
+                        ol_w_id: w_id,
+                        ol_number,
+                        ol_i_id: rand_range(rng, 1, ITEM_COUNT),
+                        ol_supply_w_id: w_id,
+                        ol_delivery_d: if is_delivered {
+                            Some(date.to_string())
+                        } else {
+                            None
+                        },
+                        ol_quantity: 5,
+                        ol_amount: if is_delivered {
+                            decimal(0, 2)
+                        } else {
+                            decimal(rand_range(rng, 1, 999_999), 2)
+                        },
+                        ol_dist_info: rand_a_string(rng, 24, 24),
+                    })
+                    .expect("Error writing an ORDER-LINE row");
+            }
+            if !is_delivered {
+                new_order_wtr
+                    .serialize(NewOrderRow {
+                        no_o_id: o_id,
+                        no_d_id: d_id,
+                        no_w_id: w_id,
+                    })
+                    .expect("Error writing a NEW-ORDER row");
+            }
+        }
+    }
+}
+
+fn rand_address<R: Rng>(rng: &mut R) -> Address {
+    Address(
+        rand_a_string(rng, 10, 20),
+        rand_a_string(rng, 10, 20),
+        rand_a_string(rng, 10, 20),
+        rand_a_string(rng, 2, 2).to_uppercase(),
+        rand_zip(rng),
+    )
+}
+
+/// Fixed point decimal text out of an integer number of the smallest units
+fn decimal(units: u32, scale: u32) -> String {
+    let divisor = 10u32.pow(scale);
+    format!(
+        "{}.{:0width$}",
+        units / divisor,
+        units % divisor,
+        width = scale as usize
+    )
+}
+
+fn table_writer(out_dir: &Path, table: &str, w_id: Option<u32>) -> csv::Writer<fs::File> {
+    let path = out_dir.join(table_file_name(table, w_id));
+    csv::Writer::from_path(&path)
+        .unwrap_or_else(|e| panic!("Error creating table file {:?}: {}", &path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_dir_template_is_rendered() {
+        let dir = tempfile::tempdir().unwrap();
+        populate(
+            vec![2],
+            Some(42),
+            Some(100),
+            Vec::new(),
+            Some(format!("{}/{{label}}/{{ts}}", dir.path().to_str().unwrap())),
+            Some("pg".to_string()),
+        );
+
+        let out_dirs: Vec<PathBuf> = fs::read_dir(dir.path().join("pg"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(out_dirs.len(), 1);
+        let ts = out_dirs[0].file_name().unwrap().to_str().unwrap();
+        assert!(
+            chrono::NaiveDateTime::parse_from_str(ts, TS_FORMAT).is_ok(),
+            "{}",
+            ts
+        );
+        let info: PopulationInfo = serde_yaml::from_str(
+            &fs::read_to_string(out_dirs[0].join(POPULATION_FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            (info.warehouse_id_list, info.seed, info.c_last_load),
+            (vec![2], 42, 100)
+        );
+        assert!(out_dirs[0].join(table_file_name("stock", Some(2))).exists());
+    }
+}
@@ -0,0 +1,428 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cfg::TransactionType;
+use crate::util::Error;
+
+/// Number of cards in the default deck: 10 NewOrder, 10 Payment and one of each other type
+pub const DECK_SIZE: u32 = 23;
+
+/// Workload mix, keying and think times per transaction type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WorkloadProfile {
+    /// Number of cards in a deck, every deck holds the mix on its own
+    #[serde(default = "default_deck_size")]
+    pub deck_size: u32,
+    pub new_order: TransactionProfile,
+    pub payment: TransactionProfile,
+    pub order_status: TransactionProfile,
+    pub delivery: TransactionProfile,
+    pub stock_level: TransactionProfile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionProfile {
+    /// Share of the transaction type in a deck. The NewOrder share is whatever is left by the other types
+    pub mix_percent: f64,
+    pub keying_time_ms: u32,
+    pub think_time_mean_ms: u32,
+    /// Chance of a rollback drawn for every single transaction
+    #[serde(default)]
+    pub rbk_percent: u32,
+    /// Response time the terminal cycle time is estimated with
+    #[serde(default)]
+    pub expected_rt_ms: u32,
+}
+
+/// TPC-C minimums a compliant profile must meet, see Clauses 5.2.3, 5.2.5.7 and 2.4.1.4
+struct TransactionMinimums {
+    mix_percent: f64,
+    keying_time_ms: u32,
+    think_time_mean_ms: u32,
+    rbk_percent: u32,
+}
+
+/// Scale factors of the profile's keying and mean think times, e.g. 0 for zero-think max throughput runs.
+/// Any factor other than 1 breaks the TPC-C rules
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    pub think_time: f64,
+    pub keying_time: f64,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale {
+            think_time: 1.,
+            keying_time: 1.,
+        }
+    }
+}
+
+impl TimeScale {
+    pub fn is_scaled(&self) -> bool {
+        *self != TimeScale::default()
+    }
+
+    /// Factors that cannot be applied
+    pub fn check(&self) -> Result<(), Error> {
+        match [("Think", self.think_time), ("Keying", self.keying_time)]
+            .iter()
+            .find(|(_, factor)| !factor.is_finite() || *factor < 0.)
+        {
+            Some((name, factor)) => Err(Error {
+                err_msg: format!("{} time scale must be 0 or more, got {}", name, factor),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Scaled times, see TPC-C Clause 5.2.5.7
+    pub fn violations(&self) -> Vec<String> {
+        [("Think", self.think_time), ("Keying", self.keying_time)]
+            .iter()
+            .filter(|(_, factor)| *factor != 1.)
+            .map(|(name, factor)| {
+                format!(
+                    "{} times are scaled by {}, the TPC-C minimums no longer hold",
+                    name, factor
+                )
+            })
+            .collect()
+    }
+}
+
+fn default_deck_size() -> u32 {
+    DECK_SIZE
+}
+
+impl Default for WorkloadProfile {
+    fn default() -> Self {
+        WorkloadProfile {
+            deck_size: DECK_SIZE,
+            new_order: TransactionProfile {
+                mix_percent: 44.,
+                keying_time_ms: 18_000,
+                think_time_mean_ms: 12_000,
+                rbk_percent: 1,
+                expected_rt_ms: 500,
+            },
+            payment: TransactionProfile {
+                mix_percent: 44.,
+                keying_time_ms: 3_000,
+                think_time_mean_ms: 12_000,
+                rbk_percent: 0,
+                expected_rt_ms: 300,
+            },
+            order_status: TransactionProfile {
+                mix_percent: 4.,
+                keying_time_ms: 2_000,
+                think_time_mean_ms: 10_000,
+                rbk_percent: 0,
+                expected_rt_ms: 300,
+            },
+            delivery: TransactionProfile {
+                mix_percent: 4.,
+                keying_time_ms: 2_000,
+                think_time_mean_ms: 5_000,
+                rbk_percent: 0,
+                expected_rt_ms: 100,
+            },
+            stock_level: TransactionProfile {
+                mix_percent: 4.,
+                keying_time_ms: 2_000,
+                think_time_mean_ms: 5_000,
+                rbk_percent: 0,
+                expected_rt_ms: 1_000,
+            },
+        }
+    }
+}
+
+impl WorkloadProfile {
+    /// Read a profile from a YAML or TOML file, the format is chosen by the file extension
+    pub fn from_file(path: &str) -> Result<WorkloadProfile, Error> {
+        let content = fs::read_to_string(path).map_err(|e| Error {
+            err_msg: format!("Error reading workload profile '{}': {}", path, e),
+        })?;
+        let profile: WorkloadProfile = match Path::new(path).extension() {
+            Some(ext) if ext == "toml" => toml::from_str(&content).map_err(|e| e.to_string()),
+            _ => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| Error {
+            err_msg: format!("Workload profile '{}' has an incorrect format: {}", path, e),
+        })?;
+        profile.check()?;
+        Ok(profile)
+    }
+
+    /// Profile with the keying and mean think times scaled, rounded to whole milliseconds
+    pub fn scaled(&self, scale: &TimeScale) -> WorkloadProfile {
+        let scale_tx = |p: &TransactionProfile| TransactionProfile {
+            keying_time_ms: (p.keying_time_ms as f64 * scale.keying_time).round() as u32,
+            think_time_mean_ms: (p.think_time_mean_ms as f64 * scale.think_time).round() as u32,
+            ..p.clone()
+        };
+        WorkloadProfile {
+            deck_size: self.deck_size,
+            new_order: scale_tx(&self.new_order),
+            payment: scale_tx(&self.payment),
+            order_status: scale_tx(&self.order_status),
+            delivery: scale_tx(&self.delivery),
+            stock_level: scale_tx(&self.stock_level),
+        }
+    }
+
+    pub fn get(&self, tx_type: &TransactionType) -> &TransactionProfile {
+        match tx_type {
+            TransactionType::NewOrder => &self.new_order,
+            TransactionType::Payment => &self.payment,
+            TransactionType::OrderStatus => &self.order_status,
+            TransactionType::Delivery => &self.delivery,
+            TransactionType::StockLevel => &self.stock_level,
+        }
+    }
+
+    /// Number of cards of the transaction type in a deck of `deck_size` cards
+    pub fn card_count(&self, tx_type: &TransactionType, deck_size: u32) -> u32 {
+        let fraction = |p: &TransactionProfile| match p.mix_percent {
+            m if m <= 0. => 0,
+            m => std::cmp::max(1, (deck_size as f64 * m / 100.) as u32),
+        };
+        match tx_type {
+            TransactionType::NewOrder => {
+                deck_size
+                    - TransactionType::iter()
+                        .filter(|t| **t != TransactionType::NewOrder)
+                        .map(|t| fraction(self.get(t)))
+                        .sum::<u32>()
+            }
+            _ => fraction(self.get(tx_type)),
+        }
+    }
+
+    /// Expected time of a full deck: keying, mean think and expected response times of every card
+    pub fn deck_cycle_time_ms(&self) -> u64 {
+        TransactionType::iter()
+            .map(|tx_type| {
+                let p = self.get(tx_type);
+                self.card_count(tx_type, self.deck_size) as u64
+                    * (p.keying_time_ms + p.think_time_mean_ms + p.expected_rt_ms) as u64
+            })
+            .sum()
+    }
+
+    /// Structural checks no override can skip
+    fn check(&self) -> Result<(), Error> {
+        let mix_sum: f64 = TransactionType::iter()
+            .map(|t| self.get(t).mix_percent)
+            .sum();
+        if (mix_sum - 100.).abs() > 0.01 {
+            return Err(Error {
+                err_msg: format!("Mix percentages must sum up to 100, got {}", mix_sum),
+            });
+        }
+        let non_new_order_cards: u32 = TransactionType::iter()
+            .filter(|t| **t != TransactionType::NewOrder)
+            .map(|t| self.card_count(t, self.deck_size))
+            .sum();
+        if non_new_order_cards > self.deck_size {
+            return Err(Error {
+                err_msg: format!(
+                    "Deck size {} is too small for the mix percentages",
+                    self.deck_size
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Deviations from the TPC-C minimums. An empty list means the profile is compliant
+    pub fn violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        TransactionType::iter().for_each(|tx_type| {
+            let p = self.get(tx_type);
+            let min = minimums(tx_type);
+            if let Some(violation) = mix_violation(
+                tx_type,
+                self.card_count(tx_type, self.deck_size),
+                self.deck_size,
+            ) {
+                violations.push(violation);
+            }
+            if p.keying_time_ms < min.keying_time_ms {
+                violations.push(format!(
+                    "{:?} keying time {} ms is below the minimum of {} ms",
+                    tx_type, p.keying_time_ms, min.keying_time_ms
+                ));
+            }
+            if p.think_time_mean_ms < min.think_time_mean_ms {
+                violations.push(format!(
+                    "{:?} mean think time {} ms is below the minimum of {} ms",
+                    tx_type, p.think_time_mean_ms, min.think_time_mean_ms
+                ));
+            }
+            if p.rbk_percent != min.rbk_percent {
+                violations.push(format!(
+                    "{:?} rollback rate {}% differs from the required {}%",
+                    tx_type, p.rbk_percent, min.rbk_percent
+                ));
+            }
+        });
+        violations
+    }
+}
+
+/// Deviation of a deck's share of the transaction type from the TPC-C minimum mix, if any
+pub fn mix_violation(tx_type: &TransactionType, card_count: u32, deck_size: u32) -> Option<String> {
+    let min_percent = minimums(tx_type).mix_percent;
+    let mix_percent = card_count as f64 * 100. / deck_size as f64;
+    if mix_percent < min_percent {
+        Some(format!(
+            "{:?} mix {:.2}% is below the minimum of {}%",
+            tx_type, mix_percent, min_percent
+        ))
+    } else {
+        None
+    }
+}
+
+fn minimums(tx_type: &TransactionType) -> TransactionMinimums {
+    match tx_type {
+        TransactionType::NewOrder => TransactionMinimums {
+            mix_percent: 0.,
+            keying_time_ms: 18_000,
+            think_time_mean_ms: 12_000,
+            rbk_percent: 1,
+        },
+        TransactionType::Payment => TransactionMinimums {
+            mix_percent: 43.,
+            keying_time_ms: 3_000,
+            think_time_mean_ms: 12_000,
+            rbk_percent: 0,
+        },
+        TransactionType::OrderStatus => TransactionMinimums {
+            mix_percent: 4.,
+            keying_time_ms: 2_000,
+            think_time_mean_ms: 10_000,
+            rbk_percent: 0,
+        },
+        TransactionType::Delivery | TransactionType::StockLevel => TransactionMinimums {
+            mix_percent: 4.,
+            keying_time_ms: 2_000,
+            think_time_mean_ms: 5_000,
+            rbk_percent: 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_profile_is_compliant() {
+        let profile = WorkloadProfile::default();
+        assert!(profile.check().is_ok());
+        assert_eq!(profile.violations(), Vec::<String>::new());
+        let cards: Vec<u32> = TransactionType::iter()
+            .map(|tx_type| profile.card_count(tx_type, DECK_SIZE))
+            .collect();
+        assert_eq!(cards, vec![10, 10, 1, 1, 1]);
+    }
+
+    #[test]
+    fn broken_profiles_give_violations() {
+        // 10 cards: 4 Payment, 4 NewOrder and one of each other type
+        let mut profile = WorkloadProfile {
+            deck_size: 10,
+            ..WorkloadProfile::default()
+        };
+        profile.order_status.think_time_mean_ms = 9_999;
+        profile.new_order.keying_time_ms = 0;
+        profile.delivery.rbk_percent = 1;
+        assert!(profile.check().is_ok());
+        assert_eq!(
+            profile.violations(),
+            vec![
+                "NewOrder keying time 0 ms is below the minimum of 18000 ms",
+                "Payment mix 40.00% is below the minimum of 43%",
+                "OrderStatus mean think time 9999 ms is below the minimum of 10000 ms",
+                "Delivery rollback rate 1% differs from the required 0%",
+            ]
+        );
+
+        let mut profile = WorkloadProfile::default();
+        profile.payment.mix_percent = 40.;
+        profile.stock_level.mix_percent = 8.;
+        assert_eq!(
+            profile.card_count(&TransactionType::StockLevel, DECK_SIZE),
+            1
+        );
+        assert_eq!(
+            profile.violations(),
+            vec!["Payment mix 39.13% is below the minimum of 43%"]
+        );
+    }
+
+    #[test]
+    fn malformed_profiles_are_rejected() {
+        let mut profile = WorkloadProfile::default();
+        profile.payment.mix_percent = 50.;
+        let err = profile.check().unwrap_err().err_msg;
+        assert_eq!(err, "Mix percentages must sum up to 100, got 106");
+        let profile = WorkloadProfile {
+            deck_size: 3,
+            ..WorkloadProfile::default()
+        };
+        let err = profile.check().unwrap_err().err_msg;
+        assert_eq!(err, "Deck size 3 is too small for the mix percentages");
+    }
+
+    #[test]
+    fn yaml_and_toml_profiles_load_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        // The deck size is left out, the default one is taken
+        let types = [
+            ("new_order", 45, 18_000, 12_000, 1, 500),
+            ("payment", 43, 3_000, 12_000, 0, 300),
+            ("order_status", 4, 2_000, 10_000, 0, 300),
+            ("delivery", 4, 2_000, 6_000, 0, 100),
+            ("stock_level", 4, 2_000, 5_000, 0, 0),
+        ];
+        let yaml: String = types
+            .iter()
+            .map(|(name, mix, keying, think, rbk, rt)| {
+                format!(
+                    "{}:\n  mix_percent: {}\n  keying_time_ms: {}\n  think_time_mean_ms: {}\n  rbk_percent: {}\n  expected_rt_ms: {}\n",
+                    name, mix, keying, think, rbk, rt
+                )
+            })
+            .collect();
+        let toml: String = types
+            .iter()
+            .map(|(name, mix, keying, think, rbk, rt)| {
+                format!(
+                    "[{}]\nmix_percent = {}\nkeying_time_ms = {}\nthink_time_mean_ms = {}\nrbk_percent = {}\nexpected_rt_ms = {}\n",
+                    name, mix, keying, think, rbk, rt
+                )
+            })
+            .collect();
+        let yaml_path = dir.path().join("profile.yaml");
+        let toml_path = dir.path().join("profile.toml");
+        fs::write(&yaml_path, yaml).unwrap();
+        fs::write(&toml_path, toml).unwrap();
+
+        let mut expected = WorkloadProfile::default();
+        expected.new_order.mix_percent = 45.;
+        expected.payment.mix_percent = 43.;
+        expected.delivery.think_time_mean_ms = 6_000;
+        expected.stock_level.expected_rt_ms = 0;
+        let from_yaml = WorkloadProfile::from_file(yaml_path.to_str().unwrap()).unwrap();
+        let from_toml = WorkloadProfile::from_file(toml_path.to_str().unwrap()).unwrap();
+        assert_eq!(from_yaml, expected);
+        assert_eq!(from_toml, expected);
+    }
+}
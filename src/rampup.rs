@@ -0,0 +1,95 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+arg_enum! {
+    /// How the terminal start times are spread over the ramp-up.
+    /// 'None' starts all terminals at once, 'Linear' one after another at even intervals,
+    /// 'Stepped' in a number of even groups and 'Waves' one warehouse after another
+    #[derive(StructOpt, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+    pub enum RampUpMode {
+        None,
+        Linear,
+        Stepped,
+        Waves
+    }
+}
+
+/// Ramp-up plan of a run, the terminal start delays are derived from it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RampUpPlan {
+    pub mode: RampUpMode,
+    /// Start delay of the last terminal group
+    pub duration_ms: u64,
+    /// Number of groups for the 'Stepped' mode
+    pub steps: u32,
+}
+
+impl RampUpPlan {
+    pub fn new(mode: RampUpMode, duration: Duration, steps: u32) -> Self {
+        if mode == RampUpMode::Stepped && steps == 0 {
+            panic!("Stepped ramp-up requires at least one step");
+        }
+        RampUpPlan {
+            mode,
+            duration_ms: duration.as_millis() as u64,
+            steps,
+        }
+    }
+
+    /// Start delay of the terminal number `term_idx` out of `term_total` belonging to the warehouse
+    /// number `wh_idx` out of `wh_total`. Both numbers count from zero in the generation order
+    pub fn start_delay_ms(
+        &self,
+        term_idx: u64,
+        term_total: u64,
+        wh_idx: u64,
+        wh_total: u64,
+    ) -> u64 {
+        match self.mode {
+            RampUpMode::None => 0,
+            RampUpMode::Linear => group_delay_ms(self.duration_ms, term_idx, term_total),
+            RampUpMode::Stepped => {
+                let steps = self.steps as u64;
+                let step = term_idx * steps / term_total;
+                group_delay_ms(self.duration_ms, step, steps)
+            }
+            RampUpMode::Waves => group_delay_ms(self.duration_ms, wh_idx, wh_total),
+        }
+    }
+
+    /// Start delay of the terminal starting last, the plan delays the later terminals and warehouses more
+    pub fn last_start_delay_ms(&self, term_total: u64, wh_total: u64) -> u64 {
+        match term_total {
+            0 => 0,
+            _ => self.start_delay_ms(
+                term_total - 1,
+                term_total,
+                wh_total.saturating_sub(1),
+                wh_total,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for RampUpPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let duration = humantime::format_duration(Duration::from_millis(self.duration_ms));
+        match self.mode {
+            RampUpMode::None => write!(f, "none, every terminal starts at once"),
+            RampUpMode::Linear => write!(f, "terminals one after another over {}", duration),
+            RampUpMode::Stepped => write!(f, "{} steps over {}", self.steps, duration),
+            RampUpMode::Waves => write!(f, "warehouses one after another over {}", duration),
+        }
+    }
+}
+
+/// Delay of the group `idx` out of `total` groups started at even intervals, the last one at `duration_ms`
+fn group_delay_ms(duration_ms: u64, idx: u64, total: u64) -> u64 {
+    match total {
+        0 | 1 => 0,
+        _ => duration_ms * idx / (total - 1),
+    }
+}
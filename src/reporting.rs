@@ -1,17 +1,15 @@
-use std::{fs, io, thread};
+use std::{fs, thread};
 use std::cell::{RefCell, RefMut};
 use std::cmp::*;
 use std::collections::HashMap;
 use std::error::Error;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{Read, Seek, Write};
-use std::iter::Enumerate;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Barrier, Mutex};
 use std::time::Duration;
 
-use average::{Estimate, Max, Mean, Quantile};
 use chrono::{DateTime, Local};
 use crossbeam_channel::unbounded;
 use crossbeam_deque::{Steal, Worker};
@@ -23,15 +21,15 @@ use structopt::StructOpt;
 use glob::glob;
 
 use crate::cfg::*;
+use crate::manifest::{RunManifest, MANIFEST_FILE_NAME};
+use crate::naming::{render, NameVars, DEFAULT_REPORT_DIR, TS_FORMAT};
 use crate::cfg::TransactionType::*;
 use crate::terminal::*;
 
 #[derive(Debug)]
 pub struct TermGroupParams {
-    pub term_count: u32,
     pub log_files_valid: Vec<String>,
     pub earliest_start_time_ms: u64,
-    pub latest_start_time_ms: u64,
     pub steady_begin_time_ms: u64,
     pub steady_end_time_ms: u64,
 }
@@ -47,12 +45,15 @@ arg_enum! {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ReportingData {
-    tx_data: Box<Vec<TransactionData>>,
+    tx_data: Vec<TransactionData>,
     total_tx_data: Box<ThroughputData>,
     tx_rt_tpm_series: Vec<[u64; 2]>,
     total_tpmc: u64,
     total_tx_count: u64,
     terminal_count: usize,
+    /// TPC-C rules the run breaks, as recorded by its run manifest
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    non_compliance: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -84,7 +85,6 @@ pub struct ThroughputData {
 }
 
 struct TxStatsNewOrderContainer {
-    tx_type: TransactionType,
     tx_rt_histo: Histogram<u64>,
     tt_histo: Histogram<u64>,
     //TODO response time vs tpmc
@@ -93,9 +93,8 @@ struct TxStatsNewOrderContainer {
 }
 
 impl TxStatsNewOrderContainer {
-    fn new(transaction_type: &TransactionType) -> Self {
+    fn new() -> Self {
         TxStatsNewOrderContainer {
-            tx_type: transaction_type.clone(),
             tx_rt_histo: Histogram::<u64>::new(5).unwrap(),
             tt_histo: Histogram::<u64>::new(5).unwrap(),
             tx_cnt_histo: Histogram::<u64>::new(5).unwrap(),
@@ -103,19 +102,22 @@ impl TxStatsNewOrderContainer {
         }
     }
 
-    fn record_tx_rt(&mut self, value: u64) -> () {
-        self.tx_rt_histo.record(value);
+    fn record_tx_rt(&mut self, value: u64) {
+        // Values beyond the histogram bounds are dropped
+        let _ = self.tx_rt_histo.record(value);
     }
 
-    fn record_tt(&mut self, value: u64) -> () {
-        self.tt_histo.record(value);
+    fn record_tt(&mut self, value: u64) {
+        // Values beyond the histogram bounds are dropped
+        let _ = self.tt_histo.record(value);
     }
 
-    fn record_tx_cnt(&mut self, value: u64) -> () {
-        self.tx_cnt_histo.record(value);
+    fn record_tx_cnt(&mut self, value: u64) {
+        // Values beyond the histogram bounds are dropped
+        let _ = self.tx_cnt_histo.record(value);
     }
 
-    fn record_steady(&mut self) -> () {
+    fn record_steady(&mut self) {
         self.steady_count += 1;
     }
 }
@@ -138,15 +140,24 @@ const TX_COUNT_SAMPLING_INTERVAL_MSEC_F: f64 = TX_COUNT_SAMPLING_INTERVAL_MSEC a
 const TPM_SAMPLING_INTERVAL_SEC: u64 = 60;
 const TPM_SAMPLING_INTERVAL_MSEC: u64 = TPM_SAMPLING_INTERVAL_SEC * 1000;
 
+/// Begin of the measurement (steady) interval relative to the first `time_started` values of the log files
+#[derive(Debug, PartialEq)]
+pub enum SteadyBegin {
+    /// Offset from the latest start, given explicitly
+    AfterLatestStart(Duration),
+    /// Offset from the earliest start, suggested by the run manifest to cover the ramp-up
+    AfterEarliestStart(Duration),
+}
+
 pub fn analyze_term_group(
-    paths: &Vec<String>,
-    steady_begin_offset: Duration,
+    paths: &[String],
+    steady_begin: &SteadyBegin,
     steady_length: Duration,
 ) -> Result<TermGroupParams, Box<dyn Error>> {
     let w: Worker<String> = Worker::new_lifo();
     paths.iter().for_each(|f| w.push(f.clone()));
 
-    let earliest_start_time_ms = Arc::new(Mutex::new(std::u64::MAX));
+    let earliest_start_time_ms = Arc::new(Mutex::new(u64::MAX));
     let latest_start_time_ms = Arc::new(Mutex::new(0));
     let path_vec: Vec<String> = Vec::new();
     let log_files_valid = Arc::new(Mutex::new(path_vec));
@@ -187,25 +198,126 @@ pub fn analyze_term_group(
     let mut est = earliest_start_time_ms.lock().unwrap();
     let lst = latest_start_time_ms.lock().unwrap();
     if let Ordering::Greater = est.cmp(&lst) {
-        *est = lst.clone();
+        *est = *lst;
     }
     Ok(TermGroupParams {
-        term_count: lfv.len() as u32,
         earliest_start_time_ms: *est,
-        latest_start_time_ms: *lst,
         log_files_valid: lfv,
-        steady_begin_time_ms: *lst + steady_begin_offset.as_millis() as u64,
+        steady_begin_time_ms: match steady_begin {
+            SteadyBegin::AfterLatestStart(offset) => *lst + offset.as_millis() as u64,
+            SteadyBegin::AfterEarliestStart(offset) => *est + offset.as_millis() as u64,
+        },
         steady_end_time_ms: *lst + steady_length.as_millis() as u64,
     })
 }
 
+/// Reasons of the previous readings followed by the new ones, each reason listed once
+fn merge_non_compliance(mut prev: Vec<String>, new: Vec<String>) -> Vec<String> {
+    new.into_iter().for_each(|reason| {
+        if !prev.contains(&reason) {
+            prev.push(reason)
+        }
+    });
+    prev
+}
+
+/// Run manifest given explicitly, or the one of the config directory the log files were written into:
+/// the launch artifacts write the logs into `logs/` of the configs of the run or of a driver host
+pub fn find_run_manifest(
+    run_manifest_path: Option<String>,
+    log_files_paths: &[String],
+) -> Option<RunManifest> {
+    if let Some(path) = run_manifest_path {
+        return Some(
+            RunManifest::from_file(Path::new(&path)).unwrap_or_else(|e| panic!("{}", e.err_msg)),
+        );
+    }
+    let manifest_path = log_files_paths.iter().find_map(|log_path| {
+        Path::new(log_path)
+            .parent()?
+            .ancestors()
+            .take(3)
+            .map(|dir| dir.join(MANIFEST_FILE_NAME))
+            .find(|path| path.is_file())
+    })?;
+    match RunManifest::from_file(&manifest_path) {
+        Ok(manifest) => {
+            println!(
+                "Run manifest {:?} found next to the log files",
+                manifest_path
+            );
+            Some(manifest)
+        }
+        Err(e) => {
+            println!("Run manifest ignored: {}", e.err_msg);
+            None
+        }
+    }
+}
+
+/// Steady-begin offset given explicitly or suggested by the run manifest.
+/// Warns when the explicit offset is shorter than the suggested one
+pub fn resolve_steady_begin(
+    steady_begin_offset: Option<Duration>,
+    run_manifest: Option<&RunManifest>,
+) -> SteadyBegin {
+    let suggested = run_manifest.map(|manifest| {
+        let offset = Duration::from_millis(manifest.suggested_steady_begin_offset_ms);
+        println!(
+            "Ramp-up: {}, suggested steady-begin offset {}",
+            manifest.ramp_up,
+            humantime::format_duration(offset)
+        );
+        if !manifest.non_compliance.is_empty() {
+            println!(
+                "Non-compliant run, the configs break the TPC-C rules:\n{}",
+                manifest.non_compliance.join("\n")
+            );
+        }
+        // The manifests written before the last start delay count the offset from the last terminal start
+        match manifest.last_start_delay_ms {
+            Some(last_start_delay_ms) => (
+                SteadyBegin::AfterEarliestStart(offset),
+                offset.saturating_sub(Duration::from_millis(last_start_delay_ms)),
+            ),
+            None => (SteadyBegin::AfterLatestStart(offset), offset),
+        }
+    });
+    match (steady_begin_offset, suggested) {
+        (Some(offset), Some((_, suggested_after_latest_start))) => {
+            if offset < suggested_after_latest_start {
+                println!(
+                    "Steady-begin offset {} is shorter than the suggested one, terminals may still be warming up",
+                    humantime::format_duration(offset)
+                );
+            }
+            SteadyBegin::AfterLatestStart(offset)
+        }
+        (Some(offset), None) => SteadyBegin::AfterLatestStart(offset),
+        (None, Some((suggested, _))) => suggested,
+        (None, None) => panic!("Either steady-begin offset or run manifest must be set"),
+    }
+}
+
 pub fn build_reports(
-    paths: &Vec<String>,
-    steady_begin_offset: Duration,
+    paths: &[String],
+    steady_begin: SteadyBegin,
     steady_length: Duration,
     report_mode: ReportMode,
     report_path: Option<String>,
+    label: Option<String>,
+    mut non_compliance: Vec<String>,
 ) {
+    let now: DateTime<Local> = Local::now();
+    let start_ts = now.format(TS_FORMAT).to_string();
+    let name_vars = NameVars {
+        ts: &start_ts,
+        label: label.as_deref(),
+        ..Default::default()
+    };
+    let report_path = report_path
+        .map(|path| render(&path, &name_vars).unwrap_or_else(|e| panic!("{}", e.err_msg)));
+
     if let ReportMode::Append = report_mode {
         if report_path.is_none() {
             panic!("report-mode 'Append' requires non-empty path")
@@ -218,18 +330,11 @@ pub fn build_reports(
         }
     }
 
-    let now: DateTime<Local> = Local::now();
-    let start_ts = now.format("%Y%m%d_%H%M%S");
-
-    let final_report_path = report_path.clone().unwrap_or(String::from(
-        ["test-reports", &format!("{}", &start_ts)]
-            .iter()
-            .collect::<PathBuf>()
-            .to_str()
-            .unwrap(),
-    ));
+    let final_report_path = report_path.clone().unwrap_or_else(|| {
+        render(DEFAULT_REPORT_DIR, &name_vars).unwrap_or_else(|e| panic!("{}", e.err_msg))
+    });
 
-    let group_params = analyze_term_group(paths, steady_begin_offset, steady_length).unwrap();
+    let group_params = analyze_term_group(paths, &steady_begin, steady_length).unwrap();
 
     let w: Worker<String> = Worker::new_lifo();
     paths.iter().for_each(|f| w.push(f.clone()));
@@ -262,7 +367,6 @@ pub fn build_reports(
     });
 
     let earliest_start_time_ms: &u64 = &group_params.earliest_start_time_ms;
-    let latest_start_time_ms: &u64 = &group_params.latest_start_time_ms;
     let steady_begin_time_ms: &u64 = &group_params.steady_begin_time_ms;
     let steady_end_time_ms: &u64 = &group_params.steady_end_time_ms;
     let steady_legth_ms: u64 = match steady_begin_time_ms.cmp(steady_end_time_ms) {
@@ -270,15 +374,11 @@ pub fn build_reports(
         _ => *steady_end_time_ms - *steady_begin_time_ms,
     };
 
-    let mut total_tpmc = 0;
     let mut total_tx_count = 0;
 
     let mut txsg: HashMap<&TransactionType, RefCell<TxStatsNewOrderContainer>> = HashMap::new();
     TransactionType::iter().for_each(|tx_type| {
-        txsg.insert(
-            &tx_type,
-            RefCell::new(TxStatsNewOrderContainer::new(&tx_type)),
-        );
+        txsg.insert(tx_type, RefCell::new(TxStatsNewOrderContainer::new()));
     });
 
     loop {
@@ -289,7 +389,6 @@ pub fn build_reports(
                 let cycle_finish_time =
 //                    cycle_start_time + record.running_time as u64 + record.think_time_ms as u64;
                     cycle_start_time + record.tx_running_time as u64 + record.think_time_ms as u64;
-                let mut is_steady = false;
 
                 let mut tx_stats_container: RefMut<TxStatsNewOrderContainer> =
                     txsg.get(&record.typ).unwrap().borrow_mut();
@@ -300,7 +399,6 @@ pub fn build_reports(
                     && cycle_finish_time >= *steady_begin_time_ms
                     && cycle_finish_time < *steady_end_time_ms
                 {
-                    is_steady = true;
                     let tx_interval_value =
                         libm::ceil(record.tx_running_time as f64 / TX_SAMPLING_INTERVAL_MSEC_F)
                             as u64
@@ -324,7 +422,7 @@ pub fn build_reports(
 
                 tx_stats_container.record_tx_cnt(tx_cnt_interval_num);
             }
-            Err(RecvError) => {
+            Err(_) => {
                 println!("Done reading files");
 
                 // All transaction types statistical variables
@@ -352,13 +450,12 @@ pub fn build_reports(
 
                 // and Throughput variables
                 let mut tx_rt_tpm_series: Vec<[u64; 2]> = Vec::new();
-                let mut curr_tpmc: u64 = 0;
-                {
+                let curr_tpmc = {
                     let curr_tx_cont_ref = txsg.get(&TransactionType::NewOrder).unwrap();
                     let curr_tx_cont = curr_tx_cont_ref.borrow_mut();
-                    let curr_steady_count = curr_tx_cont.steady_count.clone();
-                    curr_tpmc = calculate_tpmc(&steady_legth_ms, &curr_steady_count);
-                }
+                    let curr_steady_count = curr_tx_cont.steady_count;
+                    calculate_tpmc(&steady_legth_ms, &curr_steady_count)
+                };
 
                 if let ReportMode::Append = report_mode {
                     let prev_reporting_data_path: PathBuf =
@@ -366,33 +463,43 @@ pub fn build_reports(
                             .iter()
                             .collect();
                     let mut prev_data_file =
-                        File::open(&prev_reporting_data_path).expect(&format!(
-                            "Error opening previous report data file {:?}",
-                            prev_reporting_data_path
-                        ));
+                        File::open(&prev_reporting_data_path).unwrap_or_else(|e| {
+                            panic!(
+                                "Error opening previous report data file {:?}: {}",
+                                prev_reporting_data_path, e
+                            )
+                        });
                     let mut prev_reporting_data_full = String::new();
                     prev_data_file
                         .read_to_string(&mut prev_reporting_data_full)
-                        .expect(&format!(
-                            "Error reading previous report data file {:?}",
-                            prev_reporting_data_path
-                        ));
+                        .unwrap_or_else(|e| {
+                            panic!(
+                                "Error reading previous report data file {:?}: {}",
+                                prev_reporting_data_path, e
+                            )
+                        });
                     let prev_reporting_data_str =
                         &prev_reporting_data_full[DATA_VAR_PREFIX.len()..];
                     let prev_reporting_data: ReportingData =
                         serde_json::from_str(prev_reporting_data_str).unwrap();
 
+                    // The previous readings keep their reasons
+                    non_compliance = merge_non_compliance(
+                        prev_reporting_data.non_compliance.clone(),
+                        non_compliance,
+                    );
+
                     let prev_tx_rt_tpm_series = &prev_reporting_data.tx_rt_tpm_series;
                     // Скопировать старые значения
                     prev_tx_rt_tpm_series
                         .iter()
-                        .for_each(|pair| tx_rt_tpm_series.push([pair[0].clone(), pair[1].clone()]));
+                        .for_each(|pair| tx_rt_tpm_series.push([pair[0], pair[1]]));
 
                     let prev_max = prev_tx_rt_tpm_series
                         .iter()
-                        .find(|pair| pair[0] > curr_tpmc as u64);
+                        .find(|pair| pair[0] > curr_tpmc);
                     // Старый файл уже содержит значение больше текущего tpmC, надо просто дописать в него текущее tpmC
-                    if let Some(pair) = prev_max {
+                    if prev_max.is_some() {
                         println!("Appending a new tpmC reading");
                         tx_rt_tpm_series.push([curr_tpmc, tx_rt_1x]);
 
@@ -403,6 +510,7 @@ pub fn build_reports(
                             total_tpmc: prev_reporting_data.total_tpmc,
                             total_tx_count: prev_reporting_data.total_tx_count,
                             terminal_count: prev_reporting_data.terminal_count,
+                            non_compliance,
                         };
                         write_report_file(&final_report_path, &new_reporting_data);
                         return;
@@ -457,7 +565,7 @@ pub fn build_reports(
                             let count = tx_cnt_histo.count_between(lower_tpm_bound, value);
                             let count_at = tx_cnt_histo.count_at(value);
 
-                            let mut total_tpm_at = total_tpm_map.entry(value).or_insert(0);
+                            let total_tpm_at = total_tpm_map.entry(value).or_insert(0);
                             *total_tpm_at += count;
                             let total_count_at = total_tx_count_map.entry(value).or_insert(0);
                             *total_count_at += count_at;
@@ -474,13 +582,13 @@ pub fn build_reports(
                         tx_rt_data: TxRtData {
                             tx_rt_p90: tx_rt_histo.value_at_percentile(PERCENTILE_90),
                             tx_rt_mean: tx_rt_histo.mean() as u64,
-                            tx_rt_max: tx_rt_histo.max() as u64,
+                            tx_rt_max: tx_rt_histo.max(),
                             tx_rt_high,
                             tx_rt_tx_count: steady_count,
                             tt_mean: tt_histo.mean() as u64,
                             tx_rt_series,
                             tt_series,
-                            tpmc: tpmc as u64,
+                            tpmc,
                         },
                         throughput_data: ThroughputData {
                             steady_begin_time,
@@ -493,7 +601,7 @@ pub fn build_reports(
                     total_tx_count += steady_count;
                 }); // End of TransactionType loop
 
-                let mut total_tx_data: ThroughputData = ThroughputData {
+                let total_tx_data: ThroughputData = ThroughputData {
                     steady_begin_time,
                     steady_end_time,
                     tpm_series: total_tpm_map
@@ -508,7 +616,7 @@ pub fn build_reports(
                         .collect::<Vec<[u64; 2]>>(),
                 };
 
-                total_tpmc = match steady_legth_ms.cmp(&TPM_SAMPLING_INTERVAL_MSEC) {
+                let total_tpmc = match steady_legth_ms.cmp(&TPM_SAMPLING_INTERVAL_MSEC) {
                     Ordering::Greater => {
                         total_tx_count as f64
                             / (steady_legth_ms as f64 / TPM_SAMPLING_INTERVAL_MSEC as f64)
@@ -517,12 +625,13 @@ pub fn build_reports(
                 } as u64;
 
                 let reporting_data = ReportingData {
-                    tx_data: Box::new(tx_data),
+                    tx_data,
                     total_tx_data: Box::new(total_tx_data),
                     tx_rt_tpm_series,
                     total_tpmc,
                     total_tx_count,
                     terminal_count: group_params.log_files_valid.len(),
+                    non_compliance,
                 };
 
                 write_report_file(&final_report_path, &reporting_data);
@@ -539,46 +648,53 @@ pub fn build_reports(
     }
 
     fn calculate_tpmc(steady_length_ms: &u64, tx_count: &u64) -> u64 {
-        return match steady_length_ms.cmp(&TPM_SAMPLING_INTERVAL_MSEC) {
+        let tpmc = match steady_length_ms.cmp(&TPM_SAMPLING_INTERVAL_MSEC) {
             Ordering::Greater => {
                 *tx_count as f64 / (*steady_length_ms as f64 / TPM_SAMPLING_INTERVAL_MSEC as f64)
             }
             _ => *tx_count as f64 / TPM_SAMPLING_INTERVAL_SEC as f64,
-        } as u64;
+        };
+        tpmc as u64
     }
 
     fn write_report_file(report_path: &str, reporting_data: &ReportingData) {
-        fs::create_dir_all(&report_path).expect(&format!(
-            "Error creating test report directory {:?}",
-            &report_path
-        ));
+        fs::create_dir_all(report_path).unwrap_or_else(|e| {
+            panic!(
+                "Error creating test report directory {:?}: {}",
+                &report_path, e
+            )
+        });
         let data_str =
             serde_json::to_string(&reporting_data).expect("Unsupported reporting data format");
-        let data_file_path = [&report_path, DATA_FILE_NAME].iter().collect::<PathBuf>();
+        let data_file_path = [report_path, DATA_FILE_NAME].iter().collect::<PathBuf>();
         let mut data_file = File::create(&data_file_path)
-            .expect(&format!("Error creating data file {:?}", &data_file_path));
+            .unwrap_or_else(|e| panic!("Error creating data file {:?}: {}", &data_file_path, e));
         let data_file_err_msg = format!("Error writing data file {:?}", &data_file_path);
         data_file
-            .write(DATA_VAR_PREFIX.as_bytes())
+            .write_all(DATA_VAR_PREFIX.as_bytes())
             .expect(&data_file_err_msg);
         data_file
-            .write(data_str.as_bytes())
+            .write_all(data_str.as_bytes())
             .expect(&data_file_err_msg);
     }
 
     fn copy_assets(report_path: &str) {
-        fs::create_dir_all(&report_path).expect(&format!(
-            "Error creating test report directory {:?}",
-            &report_path
-        ));
+        fs::create_dir_all(report_path).unwrap_or_else(|e| {
+            panic!(
+                "Error creating test report directory {:?}: {}",
+                &report_path, e
+            )
+        });
         let copy_err_msg = "Error copying an asset file";
         ["css", "js"].iter().for_each(|dir| {
             let dir_path = ["assets", *dir, "*.*"].iter().collect::<PathBuf>();
             let mut dir_target_path = [report_path, *dir].iter().collect::<PathBuf>();
-            fs::create_dir_all(&dir_target_path).expect(&format!(
-                "Error creating asset directory {:?}",
-                &dir_target_path
-            ));
+            fs::create_dir_all(&dir_target_path).unwrap_or_else(|e| {
+                panic!(
+                    "Error creating asset directory {:?}: {}",
+                    &dir_target_path, e
+                )
+            });
             for entry in glob(dir_path.to_str().unwrap())
                 .unwrap()
                 .filter_map(Result::ok)
@@ -605,3 +721,75 @@ pub fn build_reports(
         .expect(copy_err_msg);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{gen_cfg, GenCfgParams};
+    use crate::naming::OutputNaming;
+    use crate::profile::{TimeScale, WorkloadProfile};
+    use crate::rampup::{RampUpMode, RampUpPlan};
+
+    #[test]
+    fn manifest_found_next_to_the_logs_suggests_the_offset() {
+        let run_dir = tempfile::tempdir().unwrap();
+        gen_cfg(GenCfgParams {
+            warehouse_id_list: vec![1, 2],
+            terminal_count: Some(10),
+            terminal_map: None,
+            transaction_count: Some(23),
+            duration: None,
+            margin_percent: 10,
+            seed: Some(42),
+            c_last_load: None,
+            c_last: None,
+            c_id: None,
+            ol_i_id: None,
+            profile: WorkloadProfile::default(),
+            arrival: None,
+            time_scale: TimeScale::default(),
+            non_compliant: false,
+            ramp_up: RampUpPlan::new(RampUpMode::Waves, Duration::from_secs(60), 1),
+            format: CfgFormat::Yaml,
+            bundle: None,
+            naming: OutputNaming {
+                output_dir: Some(run_dir.path().to_str().unwrap().to_string()),
+                name_template: None,
+                label: None,
+            },
+            driver_hosts: None,
+            launch: Default::default(),
+        });
+
+        // The logs of a driver host are two levels below the manifest
+        let log_path = run_dir.path().join("host1").join("logs").join("W1_T1.csv");
+        let manifest = find_run_manifest(None, &[log_path.to_str().unwrap().to_string()]).unwrap();
+        assert_eq!(manifest.last_start_delay_ms, Some(60_000));
+        assert_eq!(
+            resolve_steady_begin(None, Some(&manifest)),
+            SteadyBegin::AfterEarliestStart(Duration::from_millis(
+                manifest.suggested_steady_begin_offset_ms
+            ))
+        );
+        // An explicit offset still counts from the latest start
+        assert_eq!(
+            resolve_steady_begin(Some(Duration::from_secs(600)), Some(&manifest)),
+            SteadyBegin::AfterLatestStart(Duration::from_secs(600))
+        );
+
+        let other_dir = tempfile::tempdir().unwrap();
+        let log_path = other_dir
+            .path()
+            .join("a")
+            .join("b")
+            .join("logs")
+            .join("W1_T1.csv");
+        assert!(find_run_manifest(None, &[log_path.to_str().unwrap().to_string()]).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Either steady-begin offset or run manifest must be set")]
+    fn offset_is_required_without_a_manifest() {
+        resolve_steady_begin(None, None);
+    }
+}
@@ -0,0 +1,678 @@
+use std::fs;
+use std::path::Path;
+
+use structopt::StructOpt;
+
+use crate::populate::{table_file_name, ITEM_TABLE, WAREHOUSE_TABLES};
+
+arg_enum! {
+    /// SQL dialect of the emitted schema and load scripts
+    #[derive(StructOpt, Debug, Clone, Copy)]
+    pub enum SqlDialect {
+        Postgres,
+        MySql,
+        Sqlite
+    }
+}
+
+enum ColumnType {
+    Int,
+    SmallInt,
+    Decimal(u32, u32),
+    Char(u32),
+    Varchar(u32),
+    Timestamp,
+}
+
+use self::ColumnType::*;
+
+struct Column {
+    name: &'static str,
+    typ: ColumnType,
+    nullable: bool,
+}
+
+struct Table {
+    name: &'static str,
+    columns: Vec<Column>,
+    primary_key: &'static [&'static str],
+}
+
+struct Index {
+    name: &'static str,
+    table: &'static str,
+    columns: &'static [&'static str],
+}
+
+const SCHEMA_FILE_PREFIX: &str = "schema";
+const LOAD_FILE_PREFIX: &str = "load";
+
+/// Secondary indexes recommended for the customer lookup by last name
+/// and for the OrderStatus lookup of the latest order
+const INDEXES: [Index; 2] = [
+    Index {
+        name: "customer_last_name_idx",
+        table: "customer",
+        columns: &["c_w_id", "c_d_id", "c_last", "c_first"],
+    },
+    Index {
+        name: "orders_customer_idx",
+        table: "orders",
+        columns: &["o_w_id", "o_d_id", "o_c_id", "o_id"],
+    },
+];
+
+fn col(name: &'static str, typ: ColumnType) -> Column {
+    Column {
+        name,
+        typ,
+        nullable: false,
+    }
+}
+
+fn nullable_col(name: &'static str, typ: ColumnType) -> Column {
+    Column {
+        name,
+        typ,
+        nullable: true,
+    }
+}
+
+/// TPC-C tables, see Clause 1.3. The column order matches the populated CSV files
+fn tables() -> Vec<Table> {
+    let address = |prefix: &'static [&'static str; 5]| {
+        vec![
+            col(prefix[0], Varchar(20)),
+            col(prefix[1], Varchar(20)),
+            col(prefix[2], Varchar(20)),
+            col(prefix[3], Char(2)),
+            col(prefix[4], Char(9)),
+        ]
+    };
+    let mut warehouse = vec![col("w_id", Int), col("w_name", Varchar(10))];
+    warehouse.extend(address(&[
+        "w_street_1",
+        "w_street_2",
+        "w_city",
+        "w_state",
+        "w_zip",
+    ]));
+    warehouse.extend(vec![
+        col("w_tax", Decimal(4, 4)),
+        col("w_ytd", Decimal(12, 2)),
+    ]);
+
+    let mut district = vec![
+        col("d_id", SmallInt),
+        col("d_w_id", Int),
+        col("d_name", Varchar(10)),
+    ];
+    district.extend(address(&[
+        "d_street_1",
+        "d_street_2",
+        "d_city",
+        "d_state",
+        "d_zip",
+    ]));
+    district.extend(vec![
+        col("d_tax", Decimal(4, 4)),
+        col("d_ytd", Decimal(12, 2)),
+        col("d_next_o_id", Int),
+    ]);
+
+    let mut customer = vec![
+        col("c_id", Int),
+        col("c_d_id", SmallInt),
+        col("c_w_id", Int),
+        col("c_first", Varchar(16)),
+        col("c_middle", Char(2)),
+        col("c_last", Varchar(16)),
+    ];
+    customer.extend(address(&[
+        "c_street_1",
+        "c_street_2",
+        "c_city",
+        "c_state",
+        "c_zip",
+    ]));
+    customer.extend(vec![
+        col("c_phone", Char(16)),
+        col("c_since", Timestamp),
+        col("c_credit", Char(2)),
+        col("c_credit_lim", Decimal(12, 2)),
+        col("c_discount", Decimal(4, 4)),
+        col("c_balance", Decimal(12, 2)),
+        col("c_ytd_payment", Decimal(12, 2)),
+        col("c_payment_cnt", SmallInt),
+        col("c_delivery_cnt", SmallInt),
+        col("c_data", Varchar(500)),
+    ]);
+
+    let mut stock = vec![
+        col("s_i_id", Int),
+        col("s_w_id", Int),
+        col("s_quantity", SmallInt),
+    ];
+    for name in &[
+        "s_dist_01",
+        "s_dist_02",
+        "s_dist_03",
+        "s_dist_04",
+        "s_dist_05",
+        "s_dist_06",
+        "s_dist_07",
+        "s_dist_08",
+        "s_dist_09",
+        "s_dist_10",
+    ] {
+        stock.push(col(name, Char(24)));
+    }
+    stock.extend(vec![
+        col("s_ytd", Int),
+        col("s_order_cnt", SmallInt),
+        col("s_remote_cnt", SmallInt),
+        col("s_data", Varchar(50)),
+    ]);
+
+    vec![
+        Table {
+            name: "warehouse",
+            columns: warehouse,
+            primary_key: &["w_id"],
+        },
+        Table {
+            name: "district",
+            columns: district,
+            primary_key: &["d_w_id", "d_id"],
+        },
+        Table {
+            name: "customer",
+            columns: customer,
+            primary_key: &["c_w_id", "c_d_id", "c_id"],
+        },
+        Table {
+            name: "history",
+            columns: vec![
+                col("h_c_id", Int),
+                col("h_c_d_id", SmallInt),
+                col("h_c_w_id", Int),
+                col("h_d_id", SmallInt),
+                col("h_w_id", Int),
+                col("h_date", Timestamp),
+                col("h_amount", Decimal(6, 2)),
+                col("h_data", Varchar(24)),
+            ],
+            primary_key: &[],
+        },
+        Table {
+            name: "orders",
+            columns: vec![
+                col("o_id", Int),
+                col("o_d_id", SmallInt),
+                col("o_w_id", Int),
+                col("o_c_id", Int),
+                col("o_entry_d", Timestamp),
+                nullable_col("o_carrier_id", SmallInt),
+                col("o_ol_cnt", SmallInt),
+                col("o_all_local", SmallInt),
+            ],
+            primary_key: &["o_w_id", "o_d_id", "o_id"],
+        },
+        Table {
+            name: "new_order",
+            columns: vec![
+                col("no_o_id", Int),
+                col("no_d_id", SmallInt),
+                col("no_w_id", Int),
+            ],
+            primary_key: &["no_w_id", "no_d_id", "no_o_id"],
+        },
+        Table {
+            name: "order_line",
+            columns: vec![
+                col("ol_o_id", Int),
+                col("ol_d_id", SmallInt),
+                col("ol_w_id", Int),
+                col("ol_number", SmallInt),
+                col("ol_i_id", Int),
+                col("ol_supply_w_id", Int),
+                nullable_col("ol_delivery_d", Timestamp),
+                col("ol_quantity", SmallInt),
+                col("ol_amount", Decimal(6, 2)),
+                col("ol_dist_info", Char(24)),
+            ],
+            primary_key: &["ol_w_id", "ol_d_id", "ol_o_id", "ol_number"],
+        },
+        Table {
+            name: "item",
+            columns: vec![
+                col("i_id", Int),
+                col("i_im_id", Int),
+                col("i_name", Varchar(24)),
+                col("i_price", Decimal(5, 2)),
+                col("i_data", Varchar(50)),
+            ],
+            primary_key: &["i_id"],
+        },
+        Table {
+            name: "stock",
+            columns: stock,
+            primary_key: &["s_w_id", "s_i_id"],
+        },
+    ]
+}
+
+/// Write the schema and the load scripts of every dialect requested next to the population CSV files.
+/// The load scripts refer to the CSV files by their relative names, run them from the population directory
+pub fn write_sql_scripts(out_dir: &Path, wh_ids: &[u32], dialects: &[SqlDialect]) {
+    let tables = tables();
+    dialects.iter().for_each(|dialect| {
+        let suffix = format!("{}", dialect).to_lowercase();
+        let schema_path = out_dir.join(format!("{}_{}.sql", SCHEMA_FILE_PREFIX, suffix));
+        fs::write(&schema_path, schema_sql(*dialect, &tables))
+            .unwrap_or_else(|e| panic!("Error writing schema file {:?}: {}", &schema_path, e));
+        let load_path = out_dir.join(format!("{}_{}.sql", LOAD_FILE_PREFIX, suffix));
+        fs::write(&load_path, load_sql(*dialect, &tables, wh_ids))
+            .unwrap_or_else(|e| panic!("Error writing load script {:?}: {}", &load_path, e));
+    });
+}
+
+fn schema_sql(dialect: SqlDialect, tables: &[Table]) -> String {
+    let mut sql = String::new();
+    tables.iter().for_each(|table| {
+        let mut lines: Vec<String> = table
+            .columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "    {} {}{}",
+                    column.name,
+                    column_type_sql(dialect, &column.typ),
+                    if column.nullable { "" } else { " NOT NULL" }
+                )
+            })
+            .collect();
+        if !table.primary_key.is_empty() {
+            lines.push(format!(
+                "    PRIMARY KEY ({})",
+                table.primary_key.join(", ")
+            ));
+        }
+        sql += &format!(
+            "CREATE TABLE {} (\n{}\n);\n\n",
+            table.name,
+            lines.join(",\n")
+        );
+    });
+    INDEXES.iter().for_each(|index| {
+        sql += &format!(
+            "CREATE INDEX {} ON {} ({});\n",
+            index.name,
+            index.table,
+            index.columns.join(", ")
+        );
+    });
+    sql
+}
+
+fn column_type_sql(dialect: SqlDialect, typ: &ColumnType) -> String {
+    match (dialect, typ) {
+        (SqlDialect::Sqlite, Int) | (SqlDialect::Sqlite, SmallInt) => "INTEGER".to_string(),
+        (SqlDialect::Sqlite, Decimal(_, _)) => "NUMERIC".to_string(),
+        (SqlDialect::Sqlite, _) => "TEXT".to_string(),
+        (_, Int) => "INTEGER".to_string(),
+        (_, SmallInt) => "SMALLINT".to_string(),
+        (_, Decimal(precision, scale)) => format!("DECIMAL({}, {})", precision, scale),
+        (_, Char(len)) => format!("CHAR({})", len),
+        (_, Varchar(len)) => format!("VARCHAR({})", len),
+        (SqlDialect::MySql, Timestamp) => "DATETIME".to_string(),
+        (_, Timestamp) => "TIMESTAMP".to_string(),
+    }
+}
+
+fn load_sql(dialect: SqlDialect, tables: &[Table], wh_ids: &[u32]) -> String {
+    let table = |name: &str| tables.iter().find(|table| table.name == name).unwrap();
+    let mut sql = load_table_sql(
+        dialect,
+        table(ITEM_TABLE),
+        &table_file_name(ITEM_TABLE, None),
+    );
+    wh_ids.iter().for_each(|w_id| {
+        WAREHOUSE_TABLES.iter().for_each(|name| {
+            sql += &load_table_sql(dialect, table(name), &table_file_name(name, Some(*w_id)));
+        });
+    });
+    sql
+}
+
+fn load_table_sql(dialect: SqlDialect, table: &Table, file_name: &str) -> String {
+    match dialect {
+        SqlDialect::Postgres => format!(
+            "\\copy {} FROM '{}' WITH (FORMAT csv, HEADER true)\n",
+            table.name, file_name
+        ),
+        SqlDialect::MySql => {
+            // Empty fields are read into variables first, MySQL would load them as zeroes otherwise
+            let columns: Vec<String> = table
+                .columns
+                .iter()
+                .map(|column| match column.nullable {
+                    true => format!("@{}", column.name),
+                    false => column.name.to_string(),
+                })
+                .collect();
+            let nulls: Vec<String> = table
+                .columns
+                .iter()
+                .filter(|column| column.nullable)
+                .map(|column| format!("{0} = NULLIF(@{0}, '')", column.name))
+                .collect();
+            format!(
+                "LOAD DATA LOCAL INFILE '{}' INTO TABLE {} FIELDS TERMINATED BY ',' OPTIONALLY ENCLOSED BY '\"' LINES TERMINATED BY '\\n' IGNORE 1 LINES ({}){};\n",
+                file_name,
+                table.name,
+                columns.join(", "),
+                match nulls.is_empty() {
+                    true => String::new(),
+                    false => format!(" SET {}", nulls.join(", ")),
+                }
+            )
+        }
+        SqlDialect::Sqlite => {
+            // The sqlite3 shell imports empty fields as empty strings
+            let nulls: Vec<String> = table
+                .columns
+                .iter()
+                .filter(|column| column.nullable)
+                .map(|column| {
+                    format!(
+                        "UPDATE {0} SET {1} = NULL WHERE {1} = '';\n",
+                        table.name, column.name
+                    )
+                })
+                .collect();
+            format!(
+                ".import --csv --skip 1 {} {}\n{}",
+                file_name,
+                table.name,
+                nulls.join("")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::term_rng;
+    use crate::populate::{gen_items, gen_warehouse};
+    use crate::tpcc::*;
+    use rusqlite::{params_from_iter, Connection};
+    use sqlparser::ast::{CopySource, CopyTarget, Statement};
+    use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect};
+    use sqlparser::parser::Parser;
+    use std::collections::HashMap;
+
+    /// Run a SQLite load script the way the sqlite3 shell does: `.import --csv --skip 1` inserts
+    /// every field as text, empty ones included, the other lines are plain SQL
+    fn run_sqlite_script(conn: &Connection, dir: &Path, script: &str) {
+        let mut sql = String::new();
+        for line in script.lines() {
+            match line.strip_prefix(".import --csv --skip 1 ") {
+                Some(args) => {
+                    conn.execute_batch(&sql).unwrap();
+                    sql.clear();
+                    let (file_name, table) = args.split_once(' ').unwrap();
+                    let mut rdr = csv::Reader::from_path(dir.join(file_name)).unwrap();
+                    let placeholders = vec!["?"; rdr.headers().unwrap().len()].join(", ");
+                    let tx = conn.unchecked_transaction().unwrap();
+                    {
+                        let mut stmt = tx
+                            .prepare(&format!("INSERT INTO {} VALUES ({})", table, placeholders))
+                            .unwrap();
+                        for record in rdr.records() {
+                            stmt.execute(params_from_iter(record.unwrap().iter()))
+                                .unwrap();
+                        }
+                    }
+                    tx.commit().unwrap();
+                }
+                None => sql += &format!("{}\n", line),
+            }
+        }
+        conn.execute_batch(&sql).unwrap();
+    }
+
+    fn count(conn: &Connection, sql: &str) -> u32 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn gen_population(dir: &Path, w_id: u32) {
+        gen_items(dir, &mut term_rng(7, 0, 0));
+        gen_warehouse(
+            dir,
+            &mut term_rng(7, w_id, 0),
+            w_id,
+            100,
+            "2019-10-01 12:00:00",
+        );
+    }
+
+    fn csv_header(dir: &Path, file_name: &str) -> Vec<String> {
+        let mut rdr = csv::Reader::from_path(dir.join(file_name)).unwrap();
+        rdr.headers().unwrap().iter().map(String::from).collect()
+    }
+
+    /// Parse a schema script with the dialect given, returning the column names of every table
+    fn parse_schema(dialect: &dyn Dialect, sql: &str) -> HashMap<String, Vec<String>> {
+        let statements = Parser::parse_sql(dialect, sql).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(statements.len(), tables().len() + INDEXES.len());
+        statements
+            .into_iter()
+            .filter_map(|statement| match statement {
+                Statement::CreateTable(table) => Some((
+                    table.name.to_string(),
+                    table
+                        .columns
+                        .iter()
+                        .map(|column| column.name.to_string())
+                        .collect(),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn postgres_scripts_parse_and_match_population() {
+        let dir = tempfile::tempdir().unwrap();
+        let w_id = 3;
+        write_sql_scripts(dir.path(), &[w_id], &[SqlDialect::Postgres]);
+        gen_population(dir.path(), w_id);
+
+        let schema = parse_schema(
+            &PostgreSqlDialect {},
+            &fs::read_to_string(dir.path().join("schema_postgres.sql")).unwrap(),
+        );
+        // \copy is the client side COPY of psql, it takes the same arguments and ends at the line end
+        let load = fs::read_to_string(dir.path().join("load_postgres.sql")).unwrap();
+        let statements: Vec<Statement> = load
+            .lines()
+            .flat_map(|line| {
+                let copy = line.replacen("\\copy ", "COPY ", 1);
+                Parser::parse_sql(&PostgreSqlDialect {}, &copy).unwrap_or_else(|e| panic!("{}", e))
+            })
+            .collect();
+        assert_eq!(statements.len(), 1 + WAREHOUSE_TABLES.len());
+        statements.iter().for_each(|statement| match statement {
+            Statement::Copy {
+                source: CopySource::Table { table_name, .. },
+                to: false,
+                target: CopyTarget::File { filename },
+                ..
+            } => assert_eq!(
+                &csv_header(dir.path(), filename),
+                &schema[&table_name.to_string()],
+                "{}",
+                filename
+            ),
+            _ => panic!("Unexpected load statement {}", statement),
+        });
+    }
+
+    #[test]
+    fn mysql_scripts_parse_and_match_population() {
+        let dir = tempfile::tempdir().unwrap();
+        let w_id = 3;
+        write_sql_scripts(dir.path(), &[w_id], &[SqlDialect::MySql]);
+        gen_population(dir.path(), w_id);
+
+        let schema = parse_schema(
+            &MySqlDialect {},
+            &fs::read_to_string(dir.path().join("schema_mysql.sql")).unwrap(),
+        );
+        // sqlparser does not know LOAD DATA INFILE, the column list and the SET clause are checked instead
+        let load = fs::read_to_string(dir.path().join("load_mysql.sql")).unwrap();
+        assert_eq!(load.lines().count(), 1 + WAREHOUSE_TABLES.len());
+        let nullable: Vec<&str> = tables()
+            .iter()
+            .flat_map(|table| table.columns.iter())
+            .filter(|column| column.nullable)
+            .map(|column| column.name)
+            .collect();
+        load.lines().for_each(|line| {
+            let rest = line
+                .strip_prefix("LOAD DATA LOCAL INFILE '")
+                .unwrap_or_else(|| panic!("Unexpected load statement {}", line));
+            let (filename, rest) = rest.split_once("' INTO TABLE ").unwrap();
+            let (table_name, rest) = rest.split_once(' ').unwrap();
+            let (columns, set) = rest
+                .split_once(" IGNORE 1 LINES (")
+                .unwrap()
+                .1
+                .strip_suffix(';')
+                .unwrap()
+                .split_once(')')
+                .unwrap();
+            let columns: Vec<&str> = columns.split(", ").collect();
+            let header = csv_header(dir.path(), filename);
+            assert_eq!(&header, &schema[table_name], "{}", filename);
+            assert_eq!(
+                columns,
+                header
+                    .iter()
+                    .map(|column| match nullable.contains(&column.as_str()) {
+                        true => format!("@{}", column),
+                        false => column.clone(),
+                    })
+                    .collect::<Vec<_>>(),
+                "{}",
+                filename
+            );
+            // Every variable read is assigned back to its column, the assignments are valid MySQL
+            let variables: Vec<&str> = columns
+                .iter()
+                .filter_map(|column| column.strip_prefix('@'))
+                .collect();
+            match set.strip_prefix(" SET ") {
+                Some(assignments) => {
+                    let update = format!("UPDATE {} SET {}", table_name, assignments);
+                    match &Parser::parse_sql(&MySqlDialect {}, &update)
+                        .unwrap_or_else(|e| panic!("{}", e))[..]
+                    {
+                        [Statement::Update { assignments, .. }] => assert_eq!(
+                            assignments
+                                .iter()
+                                .map(|assignment| assignment.target.to_string())
+                                .collect::<Vec<_>>(),
+                            variables
+                        ),
+                        _ => panic!("Unexpected SET clause {}", set),
+                    }
+                }
+                None => assert!(set.is_empty() && variables.is_empty(), "{}", line),
+            }
+        });
+    }
+
+    #[test]
+    fn sqlite_schema_loads_population() {
+        let dir = tempfile::tempdir().unwrap();
+        let w_id = 3;
+        write_sql_scripts(dir.path(), &[w_id], &[SqlDialect::Sqlite]);
+        gen_population(dir.path(), w_id);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&fs::read_to_string(dir.path().join("schema_sqlite.sql")).unwrap())
+            .unwrap();
+        run_sqlite_script(
+            &conn,
+            dir.path(),
+            &fs::read_to_string(dir.path().join("load_sqlite.sql")).unwrap(),
+        );
+
+        let orders = DISTRICTS_PER_WAREHOUSE * ORDERS_PER_DISTRICT;
+        let new_orders = DISTRICTS_PER_WAREHOUSE * (ORDERS_PER_DISTRICT - FIRST_NEW_ORDER_ID + 1);
+        let customers = DISTRICTS_PER_WAREHOUSE * CUSTOMERS_PER_DISTRICT;
+        for (table, rows) in &[
+            ("item", ITEM_COUNT),
+            ("warehouse", 1),
+            ("district", DISTRICTS_PER_WAREHOUSE),
+            ("customer", customers),
+            ("history", customers),
+            ("orders", orders),
+            ("new_order", new_orders),
+            ("stock", STOCK_PER_WAREHOUSE),
+        ] {
+            assert_eq!(
+                count(&conn, &format!("SELECT COUNT(*) FROM {}", table)),
+                *rows,
+                "{}",
+                table
+            );
+        }
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM order_line"),
+            count(&conn, "SELECT SUM(o_ol_cnt) FROM orders")
+        );
+
+        // The undelivered orders and their lines are NULL, not empty strings
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM orders WHERE o_carrier_id IS NULL"
+            ),
+            new_orders
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM orders WHERE o_carrier_id IS NOT NULL AND typeof(o_carrier_id) <> 'integer'"
+            ),
+            0
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM order_line JOIN new_order \
+                 ON ol_w_id = no_w_id AND ol_d_id = no_d_id AND ol_o_id = no_o_id \
+                 WHERE ol_delivery_d IS NOT NULL"
+            ),
+            0
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM order_line WHERE ol_delivery_d IS NULL OR ol_delivery_d = ''"
+            ),
+            count(
+                &conn,
+                "SELECT SUM(o_ol_cnt) FROM orders WHERE o_carrier_id IS NULL"
+            )
+        );
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM customer WHERE c_w_id <> 3"),
+            0
+        );
+    }
+}
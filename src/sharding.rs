@@ -0,0 +1,197 @@
+use std::cmp::{max, min};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::Error;
+
+/// Driver hosts the terminals are spread over
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DriverHosts(pub Vec<String>);
+
+/// Terminals of a warehouse assigned to a driver host, both ends are included
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WarehouseTerminals {
+    pub warehouse_id: u32,
+    pub first_terminal_id: u32,
+    pub last_terminal_id: u32,
+}
+
+/// Terminals of a driver host, its configs are in the subdirectory named after the host
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HostAssignment {
+    pub host: String,
+    pub terminals: Vec<WarehouseTerminals>,
+}
+
+/// Either a host count, the hosts are named `host1`, `host2`.. then, or a comma separated list of host names
+pub fn parse_driver_hosts(s: &str) -> Result<DriverHosts, Error> {
+    let hosts: Vec<String> = match s.parse::<u32>() {
+        Ok(0) => {
+            return Err(Error {
+                err_msg: "Driver host count must be more than 0".to_string(),
+            })
+        }
+        Ok(count) => (1..count + 1).map(|i| format!("host{}", i)).collect(),
+        Err(_) => s.split(',').map(|host| host.trim().to_string()).collect(),
+    };
+    if let Some(host) = hosts
+        .iter()
+        .find(|host| host.is_empty() || host.contains('/') || host.contains('\\'))
+    {
+        return Err(Error {
+            err_msg: format!("Driver host name '{}' is not a valid directory name", host),
+        });
+    }
+    if hosts.iter().collect::<BTreeSet<_>>().len() != hosts.len() {
+        return Err(Error {
+            err_msg: format!("Driver host list '{}' has duplicates", s),
+        });
+    }
+    Ok(DriverHosts(hosts))
+}
+
+/// Split the terminals, listed warehouse by warehouse, into a contiguous range per host.
+/// `wh_sizes` are the terminal counts of the warehouses. Whole warehouses are kept on one host
+/// unless there are fewer warehouses than hosts, the hosts get about the same number of terminals then
+pub fn partition(host_count: usize, wh_sizes: &[usize]) -> Vec<Range<usize>> {
+    let term_total: usize = wh_sizes.iter().sum();
+    if host_count > term_total {
+        panic!(
+            "{} driver hosts are more than the {} terminals generated",
+            host_count, term_total
+        );
+    }
+    if wh_sizes.len() < host_count {
+        return (0..host_count)
+            .map(|i| (i * term_total / host_count)..((i + 1) * term_total / host_count))
+            .collect();
+    }
+    // Terminals before every warehouse boundary
+    let mut bounds = vec![0];
+    wh_sizes
+        .iter()
+        .for_each(|size| bounds.push(bounds.last().unwrap() + size));
+    // The host `i` ends at the last warehouse boundary not past its share of the terminals,
+    // every host keeps at least one warehouse
+    let mut wh_ends = vec![0];
+    (1..host_count).for_each(|i| {
+        let share = i * term_total / host_count;
+        let end = bounds.iter().rposition(|b| *b <= share).unwrap();
+        let end = max(end, wh_ends.last().unwrap() + 1);
+        wh_ends.push(min(end, wh_sizes.len() - (host_count - i)));
+    });
+    wh_ends.push(wh_sizes.len());
+    wh_ends
+        .windows(2)
+        .map(|ends| bounds[ends[0]]..bounds[ends[1]])
+        .collect()
+}
+
+/// Terminals of a host grouped by warehouse
+pub fn host_assignment(host: &str, terms: &[(u32, u32)]) -> HostAssignment {
+    let mut terminals: Vec<WarehouseTerminals> = Vec::new();
+    terms.iter().for_each(|(w, t)| match terminals.last_mut() {
+        Some(last) if last.warehouse_id == *w => last.last_terminal_id = *t,
+        _ => terminals.push(WarehouseTerminals {
+            warehouse_id: *w,
+            first_terminal_id: *t,
+            last_terminal_id: *t,
+        }),
+    });
+    HostAssignment {
+        host: host.to_string(),
+        terminals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ranges are contiguous, cover every terminal and none of them is empty
+    fn assert_covers(ranges: &[Range<usize>], host_count: usize, term_total: usize) {
+        assert_eq!(ranges.len(), host_count);
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, term_total);
+        ranges
+            .windows(2)
+            .for_each(|r| assert_eq!(r[0].end, r[1].start));
+        assert!(ranges.iter().all(|r| !r.is_empty()), "{:?}", ranges);
+    }
+
+    #[test]
+    fn hosts_get_whole_warehouses_differing_by_one_at_most() {
+        for wh_count in 1..40 {
+            for host_count in 1..wh_count + 1 {
+                for wh_size in &[1, 10, 13] {
+                    let ranges = partition(host_count, &vec![*wh_size; wh_count]);
+                    assert_covers(&ranges, host_count, wh_count * wh_size);
+                    let wh_counts: Vec<usize> = ranges
+                        .iter()
+                        .map(|r| {
+                            assert_eq!(r.start % wh_size, 0, "{:?}", ranges);
+                            r.len() / wh_size
+                        })
+                        .collect();
+                    let (min_count, max_count) = (
+                        *wh_counts.iter().min().unwrap(),
+                        *wh_counts.iter().max().unwrap(),
+                    );
+                    assert!(
+                        max_count - min_count <= 1,
+                        "{} warehouses of {} over {} hosts: {:?}",
+                        wh_count,
+                        wh_size,
+                        host_count,
+                        wh_counts
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_warehouses_than_hosts_split_terminals_evenly() {
+        for (host_count, wh_sizes) in &[
+            (3, vec![10]),
+            (4, vec![10, 10]),
+            (7, vec![10, 10, 10]),
+            (10, vec![10]),
+            (5, vec![3, 4]),
+        ] {
+            let term_total = wh_sizes.iter().sum();
+            let ranges = partition(*host_count, wh_sizes);
+            assert_covers(&ranges, *host_count, term_total);
+            let lens: Vec<usize> = ranges.iter().map(|r| r.len()).collect();
+            assert!(
+                lens.iter().max().unwrap() - lens.iter().min().unwrap() <= 1,
+                "{:?} over {} hosts: {:?}",
+                wh_sizes,
+                host_count,
+                lens
+            );
+        }
+    }
+
+    #[test]
+    fn uneven_warehouses_stay_whole() {
+        let wh_sizes = [10, 20, 20, 5, 30];
+        let bounds = [0, 10, 30, 50, 55, 85];
+        for host_count in 1..wh_sizes.len() + 1 {
+            let ranges = partition(host_count, &wh_sizes);
+            assert_covers(&ranges, host_count, 85);
+            ranges
+                .iter()
+                .for_each(|r| assert!(bounds.contains(&r.start) && bounds.contains(&r.end)));
+        }
+        assert_eq!(partition(3, &[10, 20, 20]), vec![0..10, 10..30, 30..50]);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than the 3 terminals")]
+    fn more_hosts_than_terminals_panic() {
+        partition(4, &[3]);
+    }
+}
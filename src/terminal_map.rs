@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::{parse_nums, Error};
+
+/// Terminal count of every warehouse listed, 0 for a warehouse populated but left without terminals
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TerminalMap(pub BTreeMap<u32, u32>);
+
+/// Either `ids=count` entries separated by `;`, e.g. `1..5=10;7=20;8=0`, the IDs in the `--warehouse-id-list` syntax,
+/// or `@file` listing the entries one per line, `#` starts a comment
+pub fn parse_terminal_map(s: &str) -> Result<TerminalMap, Error> {
+    let (entries, source): (Vec<String>, String) = match s.strip_prefix('@') {
+        Some(path) => {
+            let content = fs::read_to_string(path).map_err(|e| Error {
+                err_msg: format!("Cannot read terminal map file '{}': {}", path, e),
+            })?;
+            (
+                content
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap().trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect(),
+                format!("file '{}'", path),
+            )
+        }
+        None => (
+            s.split(';')
+                .map(|entry| entry.trim().to_string())
+                .filter(|entry| !entry.is_empty())
+                .collect(),
+            format!("'{}'", s),
+        ),
+    };
+    let mut map = BTreeMap::new();
+    for entry in &entries {
+        let entry_err = |reason: String| Error {
+            err_msg: format!(
+                "Invalid terminal map entry '{}' in {}: {}",
+                entry, source, reason
+            ),
+        };
+        let (ids, count) = entry
+            .split_once('=')
+            .ok_or_else(|| entry_err("expected `ids=count`".to_string()))?;
+        let count = count
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| entry_err(format!("terminal count '{}' is not a number", count)))?;
+        for id in parse_nums(ids.trim())
+            .map_err(|e| entry_err(e.err_msg))?
+            .iter()
+        {
+            if map.insert(*id, count).is_some() {
+                return Err(entry_err(format!(
+                    "warehouse {} is mapped more than once",
+                    id
+                )));
+            }
+        }
+    }
+    if map.is_empty() {
+        return Err(Error {
+            err_msg: format!("Terminal map {} is empty", source),
+        });
+    }
+    Ok(TerminalMap(map))
+}
+
+/// Terminal count of every warehouse: the ones of the warehouse list get the terminal count,
+/// the terminal map adds warehouses and overrides their counts
+pub fn resolve_terminal_counts(
+    warehouse_id_list: &[u32],
+    terminal_count: Option<u32>,
+    terminal_map: Option<&TerminalMap>,
+) -> Result<BTreeMap<u32, u32>, Error> {
+    let mut counts = BTreeMap::new();
+    for w in warehouse_id_list {
+        let mapped = terminal_map.and_then(|TerminalMap(map)| map.get(w));
+        let count = mapped.cloned().or(terminal_count).ok_or_else(|| Error {
+            err_msg: format!(
+                "Warehouse {} is missing from the terminal map, set the terminal count for it",
+                w
+            ),
+        })?;
+        counts.insert(*w, count);
+    }
+    if let Some(TerminalMap(map)) = terminal_map {
+        counts.extend(map.iter().map(|(w, count)| (*w, *count)));
+    }
+    if counts.is_empty() {
+        return Err(Error {
+            err_msg: "Warehouse id list is empty".to_string(),
+        });
+    }
+    if counts.values().all(|count| *count == 0) {
+        return Err(Error {
+            err_msg: "Terminal count must be more than 0".to_string(),
+        });
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn map(entries: &[(u32, u32)]) -> BTreeMap<u32, u32> {
+        entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn maps_parse() {
+        let TerminalMap(parsed) = parse_terminal_map("1..3=10; 7=20;8=0;").unwrap();
+        assert_eq!(parsed, map(&[(1, 10), (2, 10), (3, 10), (7, 20), (8, 0)]));
+        let TerminalMap(parsed) = parse_terminal_map("1..9:4,!5=10").unwrap();
+        assert_eq!(parsed, map(&[(1, 10), (9, 10)]));
+    }
+
+    #[test]
+    fn maps_are_read_from_files() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "# rack 1\n1..2=10\n\n3=20 # the large one\n4=0\n").unwrap();
+        let TerminalMap(parsed) =
+            parse_terminal_map(&format!("@{}", file.path().to_str().unwrap())).unwrap();
+        assert_eq!(parsed, map(&[(1, 10), (2, 10), (3, 20), (4, 0)]));
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        let cases = [
+            ("1..5=10;5=20", "warehouse 5 is mapped more than once"),
+            ("1..5=10;3..7=20", "warehouse 3 is mapped more than once"),
+            ("1,1=10", "warehouse 1 is mapped more than once"),
+            ("1..5", "expected `ids=count`"),
+            ("1..5=ten", "terminal count 'ten' is not a number"),
+            ("5..1=10", "range is reversed"),
+            (";", "is empty"),
+        ];
+        for (s, reason) in &cases {
+            let err = parse_terminal_map(s).unwrap_err().err_msg;
+            assert!(err.contains(reason), "'{}': {}", s, err);
+        }
+        let err = parse_terminal_map("1..5=10;5=20").unwrap_err().err_msg;
+        assert!(
+            err.starts_with("Invalid terminal map entry '5=20'"),
+            "{}",
+            err
+        );
+        let err = parse_terminal_map("@/nonexistent/map").unwrap_err().err_msg;
+        assert!(err.contains("Cannot read terminal map file"), "{}", err);
+    }
+
+    #[test]
+    fn counts_resolve_against_the_warehouse_list() {
+        let terminal_map = parse_terminal_map("2=20;4=0;9=5").unwrap();
+        // The map overrides the listed warehouses and adds the unlisted ones
+        assert_eq!(
+            resolve_terminal_counts(&[1, 2, 3, 4], Some(10), Some(&terminal_map)).unwrap(),
+            map(&[(1, 10), (2, 20), (3, 10), (4, 0), (9, 5)])
+        );
+        // The map alone is enough
+        assert_eq!(
+            resolve_terminal_counts(&[], None, Some(&terminal_map)).unwrap(),
+            map(&[(2, 20), (4, 0), (9, 5)])
+        );
+        assert_eq!(
+            resolve_terminal_counts(&[1, 2], Some(10), None).unwrap(),
+            map(&[(1, 10), (2, 10)])
+        );
+    }
+
+    #[test]
+    fn missing_and_empty_warehouses_are_rejected() {
+        let terminal_map = parse_terminal_map("2=20").unwrap();
+        let err = resolve_terminal_counts(&[1, 2], None, Some(&terminal_map))
+            .unwrap_err()
+            .err_msg;
+        assert!(
+            err.starts_with("Warehouse 1 is missing from the terminal map"),
+            "{}",
+            err
+        );
+        let err = resolve_terminal_counts(&[], Some(10), None)
+            .unwrap_err()
+            .err_msg;
+        assert_eq!(err, "Warehouse id list is empty");
+        let idle = parse_terminal_map("1..3=0").unwrap();
+        let err = resolve_terminal_counts(&[2], Some(0), Some(&idle))
+            .unwrap_err()
+            .err_msg;
+        assert_eq!(err, "Terminal count must be more than 0");
+    }
+}
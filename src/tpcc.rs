@@ -0,0 +1,308 @@
+use std::cmp::{max, min};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::util::Error;
+
+pub const DISTRICTS_PER_WAREHOUSE: u32 = 10;
+/// Throughput cap of a warehouse with its 10 terminals, see TPC-C Clause 4.2
+pub const MAX_TPMC_PER_WAREHOUSE: f64 = 12.86;
+/// Shortest measurement interval of a reported run, two hours
+pub const MIN_MEASUREMENT_INTERVAL_MS: u64 = 2 * 60 * 60 * 1000;
+pub const CUSTOMERS_PER_DISTRICT: u32 = 3_000;
+pub const ITEM_COUNT: u32 = 100_000;
+
+/// Item id that is never populated, used to trigger a NewOrder rollback
+pub const UNUSED_ITEM_ID: u32 = ITEM_COUNT + 1;
+
+pub const MIN_ORDER_LINE_COUNT: u32 = 5;
+pub const MAX_ORDER_LINE_COUNT: u32 = 15;
+pub const MAX_ORDER_LINE_QUANTITY: u32 = 10;
+
+pub const STOCK_PER_WAREHOUSE: u32 = ITEM_COUNT;
+pub const ORDERS_PER_DISTRICT: u32 = CUSTOMERS_PER_DISTRICT;
+/// Orders starting from this id are undelivered and have a NEW-ORDER row
+pub const FIRST_NEW_ORDER_ID: u32 = 2_101;
+
+/// `A` values of the NURand function, see TPC-C Clause 2.1.6
+pub const NURAND_A_C_LAST: u32 = 255;
+pub const NURAND_A_C_ID: u32 = 1023;
+pub const NURAND_A_OL_I_ID: u32 = 8191;
+
+pub const MIN_H_AMOUNT_CENTS: u32 = 100;
+pub const MAX_H_AMOUNT_CENTS: u32 = 500_000;
+pub const MAX_CARRIER_ID: u32 = 10;
+pub const MIN_STOCK_THRESHOLD: u32 = 10;
+pub const MAX_STOCK_THRESHOLD: u32 = 20;
+
+/// Syllables customer last names are made of, see TPC-C Clause 4.3.2.3
+pub const LAST_NAME_SYLLABLES: [&str; 10] = [
+    "BAR", "OUGHT", "ABLE", "PRI", "PRES", "ESE", "ANTI", "CALLY", "ATION", "EING",
+];
+
+/// C constants of the NURand function, see TPC-C Clause 2.1.6
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NuRandConstants {
+    /// C_LAST value the database was populated with
+    pub c_last_load: u32,
+    /// Run-time values
+    pub c_last: u32,
+    pub c_id: u32,
+    pub ol_i_id: u32,
+}
+
+impl NuRandConstants {
+    /// Validate the constants provided and draw the missing ones.
+    /// The run-time C_LAST is drawn to satisfy Clause 2.1.6.1 against the load-time one
+    pub fn new<R: Rng>(
+        rng: &mut R,
+        c_last_load: Option<u32>,
+        c_last: Option<u32>,
+        c_id: Option<u32>,
+        ol_i_id: Option<u32>,
+    ) -> Result<Self, Error> {
+        let c_last_load = pick_c(rng, "C_LAST load", c_last_load, NURAND_A_C_LAST)?;
+        let c_last = match c_last {
+            Some(c_last) => {
+                pick_c(rng, "C_LAST", Some(c_last), NURAND_A_C_LAST)?;
+                if !is_valid_c_last_delta(c_last_load, c_last) {
+                    return Err(Error {
+                        err_msg: format!(
+                            "C_LAST run value {} does not fit the load value {}: the difference must be within [{} .. {}] excluding {:?}",
+                            c_last, c_last_load, MIN_C_LAST_DELTA, MAX_C_LAST_DELTA, EXCLUDED_C_LAST_DELTAS
+                        ),
+                    });
+                }
+                c_last
+            }
+            None => {
+                let valid: Vec<u32> = (0..NURAND_A_C_LAST + 1)
+                    .filter(|c| is_valid_c_last_delta(c_last_load, *c))
+                    .collect();
+                *valid.choose(rng).unwrap()
+            }
+        };
+        Ok(NuRandConstants {
+            c_last_load,
+            c_last,
+            c_id: pick_c(rng, "C_ID", c_id, NURAND_A_C_ID)?,
+            ol_i_id: pick_c(rng, "OL_I_ID", ol_i_id, NURAND_A_OL_I_ID)?,
+        })
+    }
+}
+
+pub const MIN_C_LAST_DELTA: u32 = 65;
+pub const MAX_C_LAST_DELTA: u32 = 119;
+pub const EXCLUDED_C_LAST_DELTAS: [u32; 2] = [96, 112];
+
+/// Difference between the run-time and load-time C_LAST values, see TPC-C Clause 2.1.6.1
+pub fn is_valid_c_last_delta(c_last_load: u32, c_last: u32) -> bool {
+    let delta = max(c_last_load, c_last) - min(c_last_load, c_last);
+    (MIN_C_LAST_DELTA..=MAX_C_LAST_DELTA).contains(&delta)
+        && !EXCLUDED_C_LAST_DELTAS.contains(&delta)
+}
+
+/// District a terminal is bound to, terminals 1 to 10 of a warehouse get a district each, see TPC-C Clause 5.5.1.1.
+/// The terminals beyond the 10th of a non-compliant run share the districts round-robin
+pub fn district_of_terminal(terminal_id: u32) -> u32 {
+    (terminal_id - 1) % DISTRICTS_PER_WAREHOUSE + 1
+}
+
+/// Check the terminal count maps onto the districts of a warehouse one to one, see TPC-C Clause 5.5.1.1
+pub fn terminal_count_violation(terminal_count: u32) -> Option<String> {
+    if terminal_count == DISTRICTS_PER_WAREHOUSE {
+        return None;
+    }
+    Some(format!(
+        "Terminal count {} must be {}, a terminal per district of the warehouse (TPC-C Clause 5.5.1.1){}",
+        terminal_count,
+        DISTRICTS_PER_WAREHOUSE,
+        match terminal_count {
+            0 => ", the warehouse is left without terminals",
+            count if count > DISTRICTS_PER_WAREHOUSE => ", the districts are shared round-robin",
+            _ => ", some districts get no terminal",
+        }
+    ))
+}
+
+fn pick_c<R: Rng>(rng: &mut R, name: &str, c: Option<u32>, a: u32) -> Result<u32, Error> {
+    match c {
+        Some(c) if c > a => Err(Error {
+            err_msg: format!("{} constant {} must be within [0 .. {}]", name, c, a),
+        }),
+        Some(c) => Ok(c),
+        None => Ok(rand_range(rng, 0, a)),
+    }
+}
+
+/// Non-uniform random number within [x .. y], see TPC-C Clause 2.1.6
+pub fn nurand<R: Rng>(rng: &mut R, a: u32, x: u32, y: u32, c: u32) -> u32 {
+    (((rng.gen_range(0, a + 1) | rng.gen_range(x, y + 1)) + c) % (y - x + 1)) + x
+}
+
+/// Uniform random number within [x .. y], both ends are included
+pub fn rand_range<R: Rng>(rng: &mut R, x: u32, y: u32) -> u32 {
+    rng.gen_range(x, y + 1)
+}
+
+/// Customer last name built from the three digits of `num` within [0 .. 999]
+pub fn last_name(num: u32) -> String {
+    [num / 100, (num / 10) % 10, num % 10]
+        .iter()
+        .map(|digit| LAST_NAME_SYLLABLES[*digit as usize])
+        .collect()
+}
+
+const ALPHANUMERIC: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const ORIGINAL: &str = "ORIGINAL";
+
+/// Random a-string of length within [min_len .. max_len], see TPC-C Clause 4.3.2.2
+pub fn rand_a_string<R: Rng>(rng: &mut R, min_len: u32, max_len: u32) -> String {
+    (0..rand_range(rng, min_len, max_len))
+        .map(|_| *ALPHANUMERIC.choose(rng).unwrap() as char)
+        .collect()
+}
+
+/// Random n-string of length within [min_len .. max_len], see TPC-C Clause 4.3.2.2
+pub fn rand_n_string<R: Rng>(rng: &mut R, min_len: u32, max_len: u32) -> String {
+    (0..rand_range(rng, min_len, max_len))
+        .map(|_| std::char::from_digit(rand_range(rng, 0, 9), 10).unwrap())
+        .collect()
+}
+
+/// Zip code made of four random digits and the constant "11111", see TPC-C Clause 4.3.2.7
+pub fn rand_zip<R: Rng>(rng: &mut R) -> String {
+    rand_n_string(rng, 4, 4) + "11111"
+}
+
+/// I_DATA and S_DATA value, 10% of them contain "ORIGINAL" at a random position, see TPC-C Clause 4.3.3.1
+pub fn rand_data<R: Rng>(rng: &mut R) -> String {
+    let mut data = rand_a_string(rng, 26, 50);
+    if rand_range(rng, 1, 100) <= 10 {
+        let pos = rand_range(rng, 0, data.len() as u32 - ORIGINAL.len() as u32) as usize;
+        data.replace_range(pos..pos + ORIGINAL.len(), ORIGINAL);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn c_last_deltas_follow_clause_2_1_6_1() {
+        for (c_last_load, c_last, valid) in &[
+            (0, 64, false),
+            (0, 65, true),
+            (65, 0, true),
+            (100, 219, true),
+            (219, 100, true),
+            (0, 120, false),
+            (10, 106, false),
+            (112, 0, false),
+            (0, 95, true),
+            (0, 97, true),
+            (0, 111, true),
+            (0, 113, true),
+            (200, 200, false),
+        ] {
+            assert_eq!(
+                is_valid_c_last_delta(*c_last_load, *c_last),
+                *valid,
+                "{} {}",
+                c_last_load,
+                c_last
+            );
+        }
+    }
+
+    #[test]
+    fn nurand_constants_are_validated_or_drawn() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for (c_last_load, c_last, err) in &[
+            (Some(0), Some(65), None),
+            (Some(150), Some(31), None),
+            (Some(0), Some(96), Some("does not fit")),
+            (Some(0), Some(112), Some("does not fit")),
+            (Some(0), Some(120), Some("does not fit")),
+            (Some(256), None, Some("C_LAST load constant 256")),
+            (Some(0), Some(256), Some("C_LAST constant 256")),
+        ] {
+            match (
+                NuRandConstants::new(&mut rng, *c_last_load, *c_last, None, None),
+                err,
+            ) {
+                (Ok(c), None) => assert_eq!(
+                    (Some(c.c_last_load), Some(c.c_last)),
+                    (*c_last_load, *c_last)
+                ),
+                (Err(e), Some(err)) => assert!(e.err_msg.contains(err), "{}", e.err_msg),
+                (result, _) => panic!("{:?} {:?}: {:?}", c_last_load, c_last, result),
+            }
+        }
+        assert!(NuRandConstants::new(&mut rng, None, None, Some(1024), None).is_err());
+        assert!(NuRandConstants::new(&mut rng, None, None, None, Some(8192)).is_err());
+
+        for _ in 0..1000 {
+            let c = NuRandConstants::new(&mut rng, None, None, None, None).unwrap();
+            assert!(is_valid_c_last_delta(c.c_last_load, c.c_last), "{:?}", c);
+            assert!(c.c_id <= NURAND_A_C_ID && c.ol_i_id <= NURAND_A_OL_I_ID);
+        }
+    }
+
+    #[test]
+    fn terminals_beyond_the_tenth_wrap_around_the_districts() {
+        for (terminal_id, district_id) in &[(1, 1), (10, 10), (11, 1), (20, 10), (21, 1), (35, 5)] {
+            assert_eq!(district_of_terminal(*terminal_id), *district_id);
+        }
+    }
+
+    #[test]
+    fn terminal_counts_other_than_ten_are_violations() {
+        assert_eq!(terminal_count_violation(DISTRICTS_PER_WAREHOUSE), None);
+        for (terminal_count, text) in &[
+            (
+                0,
+                "Terminal count 0 must be 10, a terminal per district of the warehouse (TPC-C Clause 5.5.1.1), the warehouse is left without terminals",
+            ),
+            (
+                7,
+                "Terminal count 7 must be 10, a terminal per district of the warehouse (TPC-C Clause 5.5.1.1), some districts get no terminal",
+            ),
+            (
+                25,
+                "Terminal count 25 must be 10, a terminal per district of the warehouse (TPC-C Clause 5.5.1.1), the districts are shared round-robin",
+            ),
+        ] {
+            assert_eq!(
+                terminal_count_violation(*terminal_count).as_deref(),
+                Some(*text)
+            );
+        }
+    }
+
+    #[test]
+    fn nurand_stays_within_the_range() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for (a, x, y, c) in &[
+            (NURAND_A_C_LAST, 0, 999, 0),
+            (NURAND_A_C_LAST, 0, 999, NURAND_A_C_LAST),
+            (NURAND_A_C_ID, 1, CUSTOMERS_PER_DISTRICT, 259),
+            (NURAND_A_OL_I_ID, 1, ITEM_COUNT, NURAND_A_OL_I_ID),
+        ] {
+            let draws: Vec<u32> = (0..100_000)
+                .map(|_| nurand(&mut rng, *a, *x, *y, *c))
+                .collect();
+            assert!(
+                draws.iter().all(|draw| (*x..=*y).contains(draw)),
+                "{} {}",
+                a,
+                c
+            );
+        }
+    }
+}
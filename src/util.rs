@@ -1,3 +1,6 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
 use std::str::FromStr;
 
 #[derive(Clone, Debug)]
@@ -5,45 +8,101 @@ pub struct Error {
     pub err_msg: String,
 }
 
-impl ToString for Error {
-    fn to_string(&self) -> String {
-        self.err_msg.clone()
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.err_msg)
     }
 }
 
+/// Parse a list of IDs: a comma separated mix of values (`8`), ranges with both ends included (`1..5`),
+/// ranges with a step (`1..100:10`), exclusions of values or ranges (`!7`, `!20..30`)
+/// and `@file` references to files listing such tokens separated by commas, spaces or new lines.
+/// `#` starts a comment in a file. The IDs are listed in the order given, the excluded ones removed.
+/// Boxed so structopt takes the list as a single value rather than a repeated option
+#[allow(clippy::box_collection)]
 pub fn parse_nums(s: &str) -> Result<Box<Vec<u32>>, Error> {
-    if !s.is_empty() {
-        if s.contains(",") {
-            let id_vec: Result<Vec<u32>, Error> = s.split(",").map(try_parse_num).collect();
-            return id_vec.map(|vec| Box::from(vec));
-        } else if s.contains("..") {
-            let split: Vec<&str> = s.split("..").collect();
-            if split.len() < 2 {
-                return Err(Error {
-                    err_msg: format!("Range must have both ends defined '{}'", s),
-                });
-            } else {
-                let range = (
-                    try_parse_num::<u32>(split.get(0).unwrap()),
-                    try_parse_num::<u32>(split.get(1).unwrap()),
-                );
-                if let (Ok(s), Ok(e)) = range {
-                    return Ok(Box::from((s..e + 1).map(|num| num).collect::<Vec<u32>>()));
-                } else {
-                    return Err(Error {
-                        err_msg: format!("Range boundaries have an incorrect format '{}'", s),
-                    });
-                }
-            }
-        } else {
-            match try_parse_num(s) {
-                Ok(id) => return Ok(Box::from(vec![id])),
-                Err(err) => return Err(err),
-            }
+    if s.trim().is_empty() {
+        return Ok(Box::from(vec![]));
+    }
+    let mut included = Vec::new();
+    let mut excluded = BTreeSet::new();
+    for token in s.split(',').map(str::trim) {
+        parse_num_token(token, true, &mut included, &mut excluded).map_err(|reason| Error {
+            err_msg: format!("Invalid token '{}' in '{}': {}", token, s, reason),
+        })?;
+    }
+    let nums: Vec<u32> = included
+        .into_iter()
+        .filter(|num| !excluded.contains(num))
+        .collect();
+    if nums.is_empty() {
+        return Err(Error {
+            err_msg: format!("List '{}' excludes every ID it includes", s),
+        });
+    }
+    Ok(Box::from(nums))
+}
+
+/// Add the IDs of a single token to the included or the excluded ones. Returns the reason the token is invalid
+fn parse_num_token(
+    token: &str,
+    allow_file: bool,
+    included: &mut Vec<u32>,
+    excluded: &mut BTreeSet<u32>,
+) -> Result<(), String> {
+    if token.is_empty() {
+        return Err("empty token".to_string());
+    }
+    if let Some(path) = token.strip_prefix('@') {
+        if !allow_file {
+            return Err("files cannot refer to other files".to_string());
         }
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("cannot read file '{}': {}", path, e))?;
+        return content
+            .lines()
+            .map(|line| line.split('#').next().unwrap())
+            .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|file_token| !file_token.is_empty())
+            .try_for_each(|file_token| {
+                parse_num_token(file_token, false, included, excluded).map_err(|reason| {
+                    format!("token '{}' of file '{}': {}", file_token, path, reason)
+                })
+            });
     }
+    match token.strip_prefix('!') {
+        Some(excluded_token) => excluded.extend(parse_num_range(excluded_token)?),
+        None => included.extend(parse_num_range(token)?),
+    }
+    Ok(())
+}
 
-    Ok(Box::from(vec![]))
+/// A single value or a range with an optional step
+fn parse_num_range(token: &str) -> Result<Vec<u32>, String> {
+    let (start, end) = match token.split_once("..") {
+        Some(bounds) => bounds,
+        None if token.contains(':') => return Err("step is allowed in ranges only".to_string()),
+        None => return parse_num(token).map(|num| vec![num]),
+    };
+    let (end, step) = match end.split_once(':') {
+        Some((end, step)) => (end, parse_num(step)?),
+        None => (end, 1),
+    };
+    if start.is_empty() || end.is_empty() {
+        return Err("range must have both ends defined".to_string());
+    }
+    let (start, end) = (parse_num(start)?, parse_num(end)?);
+    if start > end {
+        return Err(format!("range is reversed, {} is after {}", start, end));
+    }
+    if step == 0 {
+        return Err("step must be more than 0".to_string());
+    }
+    Ok((start..=end).step_by(step as usize).collect())
+}
+
+fn parse_num(s: &str) -> Result<u32, String> {
+    try_parse_num(s).map_err(|e| e.err_msg)
 }
 
 fn format_num_parse_error(s: &str) -> String {
@@ -58,3 +117,83 @@ fn try_parse_num<F: FromStr>(s: &str) -> Result<F, Error> {
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn lists_parse() {
+        let cases: &[(&str, &[u32])] = &[
+            ("7", &[7]),
+            ("1..5", &[1, 2, 3, 4, 5]),
+            ("1..5,8,10..12", &[1, 2, 3, 4, 5, 8, 10, 11, 12]),
+            (" 3 , 1..2 ", &[3, 1, 2]),
+            ("1..20:5", &[1, 6, 11, 16]),
+            ("4..4:3", &[4]),
+            ("1..10,!3..8", &[1, 2, 9, 10]),
+            ("1..10:2,!5", &[1, 3, 7, 9]),
+            ("!2,1..3", &[1, 3]),
+            ("1..3,!7", &[1, 2, 3]),
+            ("", &[]),
+        ];
+        for (list, nums) in cases {
+            assert_eq!(*parse_nums(list).unwrap(), nums.to_vec(), "'{}'", list);
+        }
+    }
+
+    #[test]
+    fn lists_are_read_from_files() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "# warehouses of the first rack\n1..3 5\n\n7,9..11:2 # odd ones\n!10\n"
+        )
+        .unwrap();
+        let path = file.path().to_str().unwrap();
+        assert_eq!(
+            *parse_nums(&format!("@{}", path)).unwrap(),
+            vec![1, 2, 3, 5, 7, 9, 11]
+        );
+        assert_eq!(
+            *parse_nums(&format!("20,@{},!1..5", path)).unwrap(),
+            vec![20, 7, 9, 11]
+        );
+    }
+
+    #[test]
+    fn invalid_lists_name_the_token() {
+        let mut nested = tempfile::NamedTempFile::new().unwrap();
+        write!(nested, "1\n@other\n").unwrap();
+        let nested = format!("@{}", nested.path().to_str().unwrap());
+        let cases: &[(&str, &str, &str)] = &[
+            ("1..5,9..3", "'9..3'", "range is reversed, 9 is after 3"),
+            ("1,,2", "''", "empty token"),
+            ("1..", "'1..'", "range must have both ends defined"),
+            ("..5", "'..5'", "range must have both ends defined"),
+            ("1..9:0", "'1..9:0'", "step must be more than 0"),
+            ("5:2", "'5:2'", "step is allowed in ranges only"),
+            ("1,x", "'x'", "Number has an incorrect format 'x'"),
+            ("1..y", "'1..y'", "Number has an incorrect format 'y'"),
+            (
+                "@/nonexistent/ids",
+                "'@/nonexistent/ids'",
+                "cannot read file",
+            ),
+            (&nested, "'@other'", "files cannot refer to other files"),
+        ];
+        for (list, token, reason) in cases {
+            let err = parse_nums(list).unwrap_err().err_msg;
+            assert!(
+                err.contains(&format!("token {}", token)),
+                "'{}': {}",
+                list,
+                err
+            );
+            assert!(err.contains(reason), "'{}': {}", list, err);
+        }
+        let err = parse_nums("3,!1..5").unwrap_err().err_msg;
+        assert_eq!(err, "List '3,!1..5' excludes every ID it includes");
+    }
+}